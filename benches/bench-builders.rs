@@ -26,8 +26,6 @@ mod digest {
 
         use mrkl::tree::Builder;
 
-        use std::iter;
-
         #[bench]
         fn pure_digest_perf_100x4k(b: &mut Bencher) {
             let block: &[u8] = &[0u8; 4 * 1024];
@@ -53,10 +51,9 @@ mod digest {
         #[bench]
         fn complete_tree_100x4k(b: &mut Bencher) {
             let block: &[u8] = &[0u8; 4 * 1024];
-            let seq: Vec<_> = iter::repeat(block).take(100).collect();
             b.iter(|| {
                 let builder = Builder::<Hasher, _>::new();
-                let tree = builder.complete_tree_from(&seq).unwrap();
+                let tree = builder.perfect_tree_of(100, block).unwrap();
                 black_box(tree);
             })
         }
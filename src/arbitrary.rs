@@ -0,0 +1,80 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Generation of arbitrary Merkle trees for property testing.
+//!
+//! This module provides [proptest][proptest] strategies that produce
+//! valid Merkle trees built from randomly generated input.
+//!
+//! [proptest]: https://crates.io/crates/proptest
+//!
+//! This module is only available if the crate has been compiled with
+//! the `arbitrary` feature.
+
+pub extern crate proptest;
+
+use self::proptest::collection::vec;
+use self::proptest::prelude::*;
+
+use hash::Hasher;
+use tree::{Builder, MerkleTree};
+
+use std::fmt::Debug;
+use std::ops::Range;
+
+/// Returns a strategy producing trees built with
+/// `Builder::complete_tree_from()` out of random byte vectors.
+///
+/// The number of leaves in the generated trees is drawn from the
+/// `leaf_count` range, and the length of each leaf's input
+/// from the `input_len` range. The hasher is created with `Default`.
+///
+/// # Panics
+///
+/// Panics if the `leaf_count` range includes 0, since an empty tree
+/// cannot be built.
+pub fn complete_trees<D>(
+    leaf_count: Range<usize>,
+    input_len: Range<usize>,
+) -> impl Strategy<Value = MerkleTree<D::HashOutput, ()>>
+where
+    D: Hasher<Vec<u8>> + Default,
+    D::HashOutput: Debug,
+{
+    assert!(leaf_count.start > 0, "leaf count range must not include 0");
+    vec(vec(any::<u8>(), input_len), leaf_count).prop_map(|inputs| {
+        let builder = Builder::<D, _>::new();
+        builder.complete_tree_from(inputs).unwrap()
+    })
+}
+
+#[cfg(all(test, feature = "digest"))]
+mod tests {
+    use super::complete_trees;
+    use super::proptest::test_runner::TestRunner;
+
+    use digest::ByteDigestHasher;
+
+    extern crate sha2;
+
+    use self::sha2::Sha256;
+
+    #[test]
+    fn generated_trees_are_complete() {
+        let mut runner = TestRunner::default();
+        let strategy = complete_trees::<ByteDigestHasher<Sha256>>(1..40, 0..8);
+        runner
+            .run(&strategy, |tree| {
+                assert!(tree.is_uniform_depth());
+                let n = tree.leaves().count();
+                assert!(n > 0 && n < 40);
+                Ok(())
+            })
+            .unwrap();
+    }
+}
@@ -17,30 +17,61 @@
 //! [IETF RFC 6962][rfc6962] and provides protection against potential
 //! second-preimage attacks: a 0 byte is prepended to the hash input of each
 //! leaf node, and a 1 byte is prepended to the concatenation of children's
-//! hash values when calculating the hash of an internal node. Note
+//! hash values when calculating the hash of an internal node. Empty leaf
+//! input is valid: the hash value of such a leaf is the digest of the lone
+//! 0 byte, which is distinct from the hash input of any internal node. Note
 //! that while RFC 6962 only uses unbalanced full binary trees, the
 //! implementation of the Merkle tree provided by this crate permits
 //! single-child nodes to achieve uniform leaf depth. Such nodes are not
 //! treated equivalent to their child by `DefaultNodeHasher`, to avoid
 //! potentially surprising behavior when any trees that are single-node
 //! chains over a subtree with the same hash value are considered equivalent
-//! to that subtree.
+//! to that subtree. Applications that do need the equivalence can wrap
+//! the node hasher into `hash::PassThroughLoneChild`. Applications that
+//! need the hash value of each internal node to be bound to the node's
+//! level in the tree can use `HeightBindingNodeHasher`. Protocols using
+//! other domain separation tags than the 0 and 1 bytes can be
+//! accommodated with the `with_tags()` constructors of the hashers.
+//! `TruncatedNodeHasher` provides shorter hash values for
+//! space-constrained applications, at the expense of collision resistance.
+//! `VarDigestHasher` works with digest functions whose output size is
+//! configured at run time.
 //!
 //! [rfc6962]: https://tools.ietf.org/html/rfc6962#section-2.1
 //!
+//! The hashers in this module are bound by the traits of the version of
+//! crate `digest` that is re-exported as `digest_hash::digest`. In that
+//! version, the trait `Digest` is implemented for all types implementing
+//! `Input`, `FixedOutput`, `Reset`, `Clone`, and `Default`, so any
+//! `Digest` implementation built against the same version of `digest` can
+//! be used with these hashers directly. Hash function crates built against
+//! a different, incompatible version of `digest` cannot be used.
+//!
+//! Type aliases and builder constructors for commonly used digest
+//! functions are provided when the crate is compiled with the `sha2`
+//! feature (SHA-256, SHA-512) or the `blake2` feature (BLAKE2b).
+//!
 //! This module is only available if the crate has been compiled with
 //! the `digest` feature, which is enabled by default.
 
-use hash::{Hasher, NodeHasher};
-use tree::Children;
+use hash::{ContextHasher, ContextNodeHasher};
+use hash::{Hasher, IncrementalHasher, LeafHashState, NodeHasher, PairHasher};
+#[cfg(any(feature = "sha2", feature = "blake2"))]
+use leaf;
+#[cfg(any(feature = "sha2", feature = "blake2"))]
+use tree::Builder;
+use tree::{Children, Node};
 
 pub extern crate digest_hash;
 pub extern crate generic_array;
 
-use self::digest_hash::digest::{FixedOutput, Input};
+use self::digest_hash::digest::{BlockInput, FixedOutput, Input};
+use self::digest_hash::digest::{InvalidOutputSize, VariableOutput};
 use self::digest_hash::{Endian, EndianInput, Hash};
+use self::generic_array::typenum::Unsigned;
 use self::generic_array::GenericArray;
 
+use std::borrow::Cow;
 use std::fmt;
 use std::fmt::Debug;
 use std::marker::PhantomData;
@@ -49,7 +80,14 @@ use std::marker::PhantomData;
 ///
 /// This implementation concatenates the hash values of the child nodes,
 /// prepended with a 1 byte, as input for the digest function.
+///
+/// Optionally, the number of child nodes can be bound into the hash,
+/// and the prefix byte can be replaced with an arbitrary tag:
+/// see the `arity_binding()` and `tag()` methods, which can be
+/// combined on the same instance.
 pub struct DefaultNodeHasher<D> {
+    arity_binding: bool,
+    node_tag: Cow<'static, [u8]>,
     phantom: PhantomData<D>,
 }
 
@@ -57,9 +95,48 @@ impl<D> DefaultNodeHasher<D> {
     /// Constructs an instance of the node hasher.
     pub fn new() -> Self {
         DefaultNodeHasher {
+            arity_binding: false,
+            node_tag: Cow::Borrowed(&[1]),
             phantom: PhantomData,
         }
     }
+
+    /// Constructs an instance of the node hasher, specifying whether
+    /// the number of child nodes should be hashed into the parent.
+    ///
+    /// This is a shorthand for `new().arity_binding(arity_binding)`.
+    pub fn with_arity_binding(arity_binding: bool) -> Self {
+        Self::new().arity_binding(arity_binding)
+    }
+
+    /// Specifies whether the number of child nodes should be hashed
+    /// into the parent.
+    ///
+    /// When arity binding is enabled, the tag prepended to the
+    /// concatenated hash values of the child nodes is followed by the
+    /// number of the child nodes, encoded as a 64-bit big-endian integer.
+    /// This makes the hash values of internal nodes unambiguous with
+    /// regard to their arity, e.g. in trees mixing single-child and
+    /// two-child nodes, as produced by `Builder::complete_tree_from()`.
+    /// Arity binding is disabled in instances created with `new()`.
+    pub fn arity_binding(mut self, arity_binding: bool) -> Self {
+        self.arity_binding = arity_binding;
+        self
+    }
+
+    /// Replaces the 1 byte prepended to the concatenated hash values
+    /// of the child nodes with the given tag.
+    ///
+    /// This is useful for interoperation with protocols that use
+    /// multi-byte tags, such as ASCII strings, for domain separation
+    /// of internal nodes. The tag does not affect arity binding.
+    pub fn tag<T>(mut self, node_tag: T) -> Self
+    where
+        T: Into<Cow<'static, [u8]>>,
+    {
+        self.node_tag = node_tag.into();
+        self
+    }
 }
 
 impl<D> Default for DefaultNodeHasher<D> {
@@ -70,13 +147,20 @@ impl<D> Default for DefaultNodeHasher<D> {
 
 impl<D> Clone for DefaultNodeHasher<D> {
     fn clone(&self) -> Self {
-        DefaultNodeHasher::new()
+        DefaultNodeHasher {
+            arity_binding: self.arity_binding,
+            node_tag: self.node_tag.clone(),
+            phantom: PhantomData,
+        }
     }
 }
 
 impl<D> Debug for DefaultNodeHasher<D> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        f.write_str("DefaultNodeHasher")
+        f.debug_struct("DefaultNodeHasher")
+            .field("arity_binding", &self.arity_binding)
+            .field("node_tag", &self.node_tag)
+            .finish()
     }
 }
 
@@ -90,9 +174,78 @@ where
     fn hash_children<'a, L>(
         &'a self,
         iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        self.hash_tagged(None, iter)
+    }
+
+    /// Returns the digest of empty input, as specified for the empty tree
+    /// by RFC 6962.
+    fn empty_root(&self) -> Self::HashOutput {
+        D::default().fixed_result()
+    }
+}
+
+impl<D, C: ?Sized> ContextNodeHasher<C> for DefaultNodeHasher<D>
+where
+    C: AsRef<[u8]>,
+    D: Default,
+    D: Input + FixedOutput,
+{
+    /// Hashes the tag, then the length of the context encoded as a
+    /// 64-bit big-endian integer, then the context bytes, followed by
+    /// the optional arity and the hash values of the child nodes.
+    fn hash_children_with_context<'a, L>(
+        &'a self,
+        context: &C,
+        iter: Children<'a, Self::HashOutput, L>,
+        _height: usize,
+    ) -> Self::HashOutput {
+        self.hash_tagged(Some(context.as_ref()), iter)
+    }
+}
+
+impl<D> PairHasher for DefaultNodeHasher<D>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        _: usize,
+        right: &Self::HashOutput,
+        _: usize,
     ) -> Self::HashOutput {
         let mut digest = D::default();
-        digest.input(&[1u8]);
+        digest.input(&self.node_tag);
+        if self.arity_binding {
+            digest.input(2u64.to_be_bytes());
+        }
+        digest.input(left);
+        digest.input(right);
+        digest.fixed_result()
+    }
+}
+
+impl<D> DefaultNodeHasher<D>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    fn hash_tagged<'a, L>(
+        &'a self,
+        context: Option<&[u8]>,
+        iter: Children<'a, GenericArray<u8, D::OutputSize>, L>,
+    ) -> GenericArray<u8, D::OutputSize> {
+        let mut digest = D::default();
+        digest.input(&self.node_tag);
+        if let Some(context) = context {
+            input_context(&mut digest, context);
+        }
+        if self.arity_binding {
+            let arity = iter.len() as u64;
+            digest.input(arity.to_be_bytes());
+        }
         for node in iter {
             digest.input(node.hash_bytes());
         }
@@ -100,6 +253,153 @@ where
     }
 }
 
+/// A `NodeHasher` implementation that binds the height of a node
+/// into its hash value.
+///
+/// The digest input consists of a 1 byte, followed by the height
+/// of the node encoded as a 64-bit big-endian integer, followed by the
+/// concatenated hash values of the child nodes. This prevents a subtree
+/// from being grafted into a tree at a different level from where it was
+/// originally hashed.
+///
+/// The height is passed by the builders through
+/// `NodeHasher::hash_children_at_height()`. The depth of a node cannot
+/// be used instead, because trees are built bottom-up and the depth
+/// of a node is not known at the time its hash value is calculated.
+/// When called through `hash_children()`, the height is
+/// obtained from the first child node.
+pub struct HeightBindingNodeHasher<D> {
+    phantom: PhantomData<D>,
+}
+
+impl<D> HeightBindingNodeHasher<D> {
+    /// Constructs an instance of the node hasher.
+    pub fn new() -> Self {
+        HeightBindingNodeHasher {
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D> Default for HeightBindingNodeHasher<D> {
+    fn default() -> Self {
+        HeightBindingNodeHasher::new()
+    }
+}
+
+impl<D> Clone for HeightBindingNodeHasher<D> {
+    fn clone(&self) -> Self {
+        HeightBindingNodeHasher::new()
+    }
+}
+
+impl<D> Debug for HeightBindingNodeHasher<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("HeightBindingNodeHasher")
+    }
+}
+
+impl<D> NodeHasher for HeightBindingNodeHasher<D>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    type HashOutput = GenericArray<u8, D::OutputSize>;
+
+    fn hash_children<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        let height = match iter.as_slice()[0] {
+            Node::Leaf(_) => 1,
+            Node::Hash(ref hn) => hn.height() + 1,
+        };
+        self.hash_children_at_height(iter, height)
+    }
+
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        hash_at_height::<D, L>(None, iter, height)
+    }
+
+    /// Returns the digest of empty input.
+    fn empty_root(&self) -> Self::HashOutput {
+        D::default().fixed_result()
+    }
+}
+
+impl<D, C: ?Sized> ContextNodeHasher<C> for HeightBindingNodeHasher<D>
+where
+    C: AsRef<[u8]>,
+    D: Default,
+    D: Input + FixedOutput,
+{
+    /// Hashes the 1 byte, then the length of the context encoded as a
+    /// 64-bit big-endian integer, then the context bytes, followed by
+    /// the height and the hash values of the child nodes.
+    fn hash_children_with_context<'a, L>(
+        &'a self,
+        context: &C,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        hash_at_height::<D, L>(Some(context.as_ref()), iter, height)
+    }
+}
+
+impl<D> PairHasher for HeightBindingNodeHasher<D>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        left_height: usize,
+        right: &Self::HashOutput,
+        _: usize,
+    ) -> Self::HashOutput {
+        let mut digest = D::default();
+        digest.input([1u8]);
+        digest.input((left_height as u64 + 1).to_be_bytes());
+        digest.input(left);
+        digest.input(right);
+        digest.fixed_result()
+    }
+}
+
+fn hash_at_height<'a, D, L>(
+    context: Option<&[u8]>,
+    iter: Children<'a, GenericArray<u8, D::OutputSize>, L>,
+    height: usize,
+) -> GenericArray<u8, D::OutputSize>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    let mut digest = D::default();
+    digest.input([1u8]);
+    if let Some(context) = context {
+        input_context(&mut digest, context);
+    }
+    digest.input((height as u64).to_be_bytes());
+    for node in iter {
+        digest.input(node.hash_bytes());
+    }
+    digest.fixed_result()
+}
+
+// Context bytes are prefixed with their length, encoded as a 64-bit
+// big-endian integer, so that the boundary between the context and
+// the data following it is unambiguous.
+fn input_context<D: Input>(digest: &mut D, context: &[u8]) {
+    digest.input((context.len() as u64).to_be_bytes());
+    digest.input(context);
+}
+
 /// Provides a cryptographic hash function implementation
 /// for hashing Merkle trees with byte order sensitive input.
 ///
@@ -117,6 +417,7 @@ where
     Nh: NodeHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
 {
     node_hasher: Nh,
+    leaf_tag: Cow<'static, [u8]>,
     phantom: PhantomData<D>,
 }
 
@@ -142,11 +443,50 @@ where
     pub fn with_node_hasher(node_hasher: Nh) -> Self {
         DigestHasher {
             node_hasher,
+            leaf_tag: Cow::Borrowed(&[0]),
             phantom: PhantomData,
         }
     }
 }
 
+impl<D> DigestHasher<D, DefaultNodeHasher<D>>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    /// Constructs a new instance of the hash extractor with the given
+    /// tags prepended to the hash input of leaf nodes and internal nodes,
+    /// respectively, in place of the 0 and 1 bytes.
+    ///
+    /// This is useful for interoperation with protocols that use
+    /// multi-byte tags, such as ASCII strings, for domain separation.
+    pub fn with_tags<Lt, Nt>(leaf_tag: Lt, node_tag: Nt) -> Self
+    where
+        Lt: Into<Cow<'static, [u8]>>,
+        Nt: Into<Cow<'static, [u8]>>,
+    {
+        DigestHasher {
+            node_hasher: DefaultNodeHasher::new().tag(node_tag),
+            leaf_tag: leaf_tag.into(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D, Nh> DigestHasher<D, Nh>
+where
+    D: BlockInput + FixedOutput,
+    Nh: NodeHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    /// Returns the block size of the digest function in bytes.
+    ///
+    /// Feeding large inputs to the digest in chunks that are multiples
+    /// of this size may be more efficient.
+    pub fn block_size() -> usize {
+        D::BlockSize::to_usize()
+    }
+}
+
 impl<D, Nh> Default for DigestHasher<D, Nh>
 where
     D: FixedOutput,
@@ -167,6 +507,7 @@ where
     fn clone(&self) -> Self {
         DigestHasher {
             node_hasher: self.node_hasher.clone(),
+            leaf_tag: self.leaf_tag.clone(),
             phantom: PhantomData,
         }
     }
@@ -195,12 +536,69 @@ where
 {
     fn hash_input(&self, input: &In) -> Self::HashOutput {
         let mut digest = D::default();
-        digest.input(&[0u8]);
+        digest.input(&self.leaf_tag);
         input.hash(&mut digest);
         digest.fixed_result()
     }
 }
 
+impl<D, Nh, C: ?Sized> ContextNodeHasher<C> for DigestHasher<D, Nh>
+where
+    D: FixedOutput,
+    Nh: ContextNodeHasher<C, HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    fn hash_children_with_context<'a, L>(
+        &'a self,
+        context: &C,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        self.node_hasher
+            .hash_children_with_context(context, iter, height)
+    }
+}
+
+impl<D, Nh, C: ?Sized, In: ?Sized> ContextHasher<C, In> for DigestHasher<D, Nh>
+where
+    C: AsRef<[u8]>,
+    In: Hash,
+    D: Default,
+    D: EndianInput + FixedOutput,
+    Nh: ContextNodeHasher<C, HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    /// Hashes the leaf tag, then the length of the context encoded as
+    /// a 64-bit big-endian integer, then the context bytes, then
+    /// the input value.
+    fn hash_input_with_context(
+        &self,
+        context: &C,
+        input: &In,
+    ) -> Self::HashOutput {
+        let mut digest = D::default();
+        digest.input(&self.leaf_tag);
+        input_context(&mut digest, context.as_ref());
+        input.hash(&mut digest);
+        digest.fixed_result()
+    }
+}
+
+impl<D, Nh> PairHasher for DigestHasher<D, Nh>
+where
+    D: FixedOutput,
+    Nh: PairHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        left_height: usize,
+        right: &Self::HashOutput,
+        right_height: usize,
+    ) -> Self::HashOutput {
+        self.node_hasher
+            .hash_pair(left, left_height, right, right_height)
+    }
+}
+
 impl<D, Nh> NodeHasher for DigestHasher<D, Nh>
 where
     D: FixedOutput,
@@ -214,6 +612,18 @@ where
     ) -> Self::HashOutput {
         self.node_hasher.hash_children(iter)
     }
+
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        self.node_hasher.hash_children_at_height(iter, height)
+    }
+
+    fn empty_root(&self) -> Self::HashOutput {
+        self.node_hasher.empty_root()
+    }
 }
 
 /// Provides a cryptographic hash function implementation
@@ -229,12 +639,39 @@ where
 /// defined by the second type parameter. The default choice should be good
 /// enough unless a specific way to derive concatenated hashes is required.
 ///
+/// The node hasher can use a different digest function from the one
+/// used to hash leaf input, as long as the output sizes are the same:
+///
+/// ```
+/// # extern crate mrkl;
+/// # #[cfg(feature = "digest")]
+/// # extern crate sha2;
+/// #
+/// # #[cfg(feature = "digest")]
+/// use mrkl::digest::{ByteDigestHasher, DefaultNodeHasher};
+/// use mrkl::tree::Builder;
+/// # #[cfg(feature = "digest")]
+/// use sha2::{Sha256, Sha512Trunc256};
+///
+/// # #[cfg(feature = "digest")]
+/// # fn main() {
+/// type Hasher = ByteDigestHasher<Sha256, DefaultNodeHasher<Sha512Trunc256>>;
+///
+/// let builder = Builder::<Hasher, _>::new();
+/// let data: &[u8] = b"the quick brown fox jumped over the lazy dog";
+/// let tree = builder.complete_tree_from(data.chunks(10)).unwrap();
+/// # let _ = tree;
+/// # }
+/// # #[cfg(not(feature = "digest"))]
+/// # fn main() { }
+/// ```
 pub struct ByteDigestHasher<D, Nh = DefaultNodeHasher<D>>
 where
     D: FixedOutput,
     Nh: NodeHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
 {
     node_hasher: Nh,
+    leaf_tag: Cow<'static, [u8]>,
     phantom: PhantomData<D>,
 }
 
@@ -260,11 +697,50 @@ where
     pub fn with_node_hasher(node_hasher: Nh) -> Self {
         ByteDigestHasher {
             node_hasher,
+            leaf_tag: Cow::Borrowed(&[0]),
             phantom: PhantomData,
         }
     }
 }
 
+impl<D> ByteDigestHasher<D, DefaultNodeHasher<D>>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    /// Constructs a new instance of the hash extractor with the given
+    /// tags prepended to the hash input of leaf nodes and internal nodes,
+    /// respectively, in place of the 0 and 1 bytes.
+    ///
+    /// This is useful for interoperation with protocols that use
+    /// multi-byte tags, such as ASCII strings, for domain separation.
+    pub fn with_tags<Lt, Nt>(leaf_tag: Lt, node_tag: Nt) -> Self
+    where
+        Lt: Into<Cow<'static, [u8]>>,
+        Nt: Into<Cow<'static, [u8]>>,
+    {
+        ByteDigestHasher {
+            node_hasher: DefaultNodeHasher::new().tag(node_tag),
+            leaf_tag: leaf_tag.into(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D, Nh> ByteDigestHasher<D, Nh>
+where
+    D: BlockInput + FixedOutput,
+    Nh: NodeHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    /// Returns the block size of the digest function in bytes.
+    ///
+    /// Feeding large inputs to the digest in chunks that are multiples
+    /// of this size may be more efficient.
+    pub fn block_size() -> usize {
+        D::BlockSize::to_usize()
+    }
+}
+
 impl<D, Nh> Default for ByteDigestHasher<D, Nh>
 where
     D: FixedOutput,
@@ -285,6 +761,7 @@ where
     fn clone(&self) -> Self {
         ByteDigestHasher {
             node_hasher: self.node_hasher.clone(),
+            leaf_tag: self.leaf_tag.clone(),
             phantom: PhantomData,
         }
     }
@@ -312,12 +789,85 @@ where
 {
     fn hash_input(&self, input: &In) -> Self::HashOutput {
         let mut digest = D::default();
-        digest.input(&[0u8]);
+        digest.input(&self.leaf_tag);
+        digest.input(input.as_ref());
+        digest.fixed_result()
+    }
+}
+
+impl<D, Nh> IncrementalHasher for ByteDigestHasher<D, Nh>
+where
+    D: Default,
+    D: Input + FixedOutput,
+    Nh: NodeHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    type LeafState = LeafDigest<D>;
+
+    fn leaf_hasher(&self) -> LeafDigest<D> {
+        let mut digest = D::default();
+        digest.input(&self.leaf_tag);
+        LeafDigest { digest }
+    }
+}
+
+impl<D, Nh, C: ?Sized> ContextNodeHasher<C> for ByteDigestHasher<D, Nh>
+where
+    D: FixedOutput,
+    Nh: ContextNodeHasher<C, HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    fn hash_children_with_context<'a, L>(
+        &'a self,
+        context: &C,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        self.node_hasher
+            .hash_children_with_context(context, iter, height)
+    }
+}
+
+impl<D, Nh, C: ?Sized, In: ?Sized> ContextHasher<C, In>
+    for ByteDigestHasher<D, Nh>
+where
+    C: AsRef<[u8]>,
+    In: AsRef<[u8]>,
+    D: Default,
+    D: Input + FixedOutput,
+    Nh: ContextNodeHasher<C, HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    /// Hashes the leaf tag, then the length of the context encoded as
+    /// a 64-bit big-endian integer, then the context bytes, then
+    /// the input bytes.
+    fn hash_input_with_context(
+        &self,
+        context: &C,
+        input: &In,
+    ) -> Self::HashOutput {
+        let mut digest = D::default();
+        digest.input(&self.leaf_tag);
+        input_context(&mut digest, context.as_ref());
         digest.input(input.as_ref());
         digest.fixed_result()
     }
 }
 
+impl<D, Nh> PairHasher for ByteDigestHasher<D, Nh>
+where
+    D: FixedOutput,
+    Nh: PairHasher<HashOutput = GenericArray<u8, D::OutputSize>>,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        left_height: usize,
+        right: &Self::HashOutput,
+        right_height: usize,
+    ) -> Self::HashOutput {
+        self.node_hasher
+            .hash_pair(left, left_height, right, right_height)
+    }
+}
+
 impl<D, Nh> NodeHasher for ByteDigestHasher<D, Nh>
 where
     D: FixedOutput,
@@ -331,21 +881,391 @@ where
     ) -> Self::HashOutput {
         self.node_hasher.hash_children(iter)
     }
+
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        self.node_hasher.hash_children_at_height(iter, height)
+    }
+
+    fn empty_root(&self) -> Self::HashOutput {
+        self.node_hasher.empty_root()
+    }
+}
+
+/// A hash extractor producing hash values truncated to the first `N` bytes
+/// of the output of a digest function.
+///
+/// This type implements both `NodeHasher` and `Hasher`, so it can be used
+/// with the builders directly. Leaf and internal node hash values are
+/// derived in the same way as by `ByteDigestHasher` with the
+/// default node hasher, except that the digest output is truncated
+/// to `N` bytes; the child hash values fed to the digest for an
+/// internal node are the truncated ones.
+///
+/// # Security
+///
+/// Truncation reduces the collision resistance of the hash function:
+/// with `N` bytes kept, collisions can be found with an effort on the
+/// order of 2<sup>4N</sup> digest operations, regardless of the
+/// strength of the full digest function. Since trees, nodes, and hash
+/// values in this crate are compared by hash value alone, a truncated
+/// hash that is too short makes it feasible to construct different
+/// trees that compare as equal. Choose `N` with the security
+/// requirements of the application in mind.
+///
+/// # Compile-time checks
+///
+/// `N` must not be larger than the output size of the digest function;
+/// otherwise, code constructing the hasher fails to compile:
+///
+/// ```compile_fail
+/// # extern crate mrkl;
+/// # extern crate sha2;
+/// # use mrkl::digest::TruncatedNodeHasher;
+/// # fn main() {
+/// let hasher = TruncatedNodeHasher::<sha2::Sha256, 33>::new();
+/// # }
+/// ```
+pub struct TruncatedNodeHasher<D, const N: usize> {
+    phantom: PhantomData<D>,
+}
+
+impl<D: FixedOutput, const N: usize> TruncatedNodeHasher<D, N> {
+    const SIZE_CHECK: () = assert!(
+        N <= D::OutputSize::USIZE,
+        "truncated size exceeds the digest output size"
+    );
+
+    /// Constructs a new instance of the hash extractor.
+    pub fn new() -> Self {
+        let () = Self::SIZE_CHECK;
+        TruncatedNodeHasher {
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns the truncated digest of empty input.
+    ///
+    /// Unlike `NodeHasher::empty_root()`, this method does not require
+    /// the hash value type `[u8; N]` to implement `Default`, so it is
+    /// available for any `N`.
+    pub fn empty_root(&self) -> [u8; N]
+    where
+        D: Default,
+    {
+        truncate(D::default())
+    }
+}
+
+impl<D: FixedOutput, const N: usize> Default for TruncatedNodeHasher<D, N> {
+    fn default() -> Self {
+        TruncatedNodeHasher::new()
+    }
+}
+
+impl<D: FixedOutput, const N: usize> Clone for TruncatedNodeHasher<D, N> {
+    fn clone(&self) -> Self {
+        TruncatedNodeHasher::new()
+    }
+}
+
+impl<D, const N: usize> Debug for TruncatedNodeHasher<D, N> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "TruncatedNodeHasher<{}>", N)
+    }
+}
+
+fn truncate<D: FixedOutput, const N: usize>(digest: D) -> [u8; N] {
+    let full = digest.fixed_result();
+    let mut out = [0u8; N];
+    out.copy_from_slice(&full[..N]);
+    out
+}
+
+impl<D, const N: usize, In: ?Sized> Hasher<In> for TruncatedNodeHasher<D, N>
+where
+    In: AsRef<[u8]>,
+    D: Default,
+    D: Input + FixedOutput,
+{
+    fn hash_input(&self, input: &In) -> Self::HashOutput {
+        let mut digest = D::default();
+        digest.input([0u8]);
+        digest.input(input.as_ref());
+        truncate(digest)
+    }
+}
+
+impl<D, const N: usize> NodeHasher for TruncatedNodeHasher<D, N>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    type HashOutput = [u8; N];
+
+    fn hash_children<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        let mut digest = D::default();
+        digest.input([1u8]);
+        for node in iter {
+            digest.input(node.hash_bytes());
+        }
+        truncate(digest)
+    }
+
+    /// Returns the truncated digest of empty input.
+    fn empty_root(&self) -> Self::HashOutput
+    where
+        Self::HashOutput: Default,
+    {
+        TruncatedNodeHasher::empty_root(self)
+    }
+}
+
+impl<D, const N: usize> PairHasher for TruncatedNodeHasher<D, N>
+where
+    D: Default,
+    D: Input + FixedOutput,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        _: usize,
+        right: &Self::HashOutput,
+        _: usize,
+    ) -> Self::HashOutput {
+        let mut digest = D::default();
+        digest.input([1u8]);
+        digest.input(left);
+        digest.input(right);
+        truncate(digest)
+    }
+}
+
+/// A hash extractor for digest functions with an output size configured
+/// at run time, such as `VarBlake2b` in crate `blake2`.
+///
+/// The output size is chosen when the hasher is constructed, and
+/// a new instance of the digest function is created with that size for
+/// every hash value. The hash values are byte vectors of that length.
+/// The leaf and internal node hash values are computed with the 0 and 1
+/// prefix bytes, like with `ByteDigestHasher` and `DefaultNodeHasher`.
+pub struct VarDigestHasher<D> {
+    output_size: usize,
+    phantom: PhantomData<D>,
+}
+
+impl<D: VariableOutput> VarDigestHasher<D> {
+    /// Constructs a new instance of the hash extractor producing hash
+    /// values of the given size in bytes.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the digest function does not support
+    /// the output size.
+    pub fn new(output_size: usize) -> Result<Self, InvalidOutputSize> {
+        D::new(output_size)?;
+        Ok(VarDigestHasher {
+            output_size,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Returns the size of the hash values in bytes.
+    pub fn output_size(&self) -> usize {
+        self.output_size
+    }
+
+    fn digest(&self) -> D {
+        D::new(self.output_size).expect("output size has been checked")
+    }
+}
+
+impl<D> Clone for VarDigestHasher<D> {
+    fn clone(&self) -> Self {
+        VarDigestHasher {
+            output_size: self.output_size,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<D> Debug for VarDigestHasher<D> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "VarDigestHasher({})", self.output_size)
+    }
+}
+
+impl<D, In: ?Sized> Hasher<In> for VarDigestHasher<D>
+where
+    In: AsRef<[u8]>,
+    D: Input + VariableOutput,
+{
+    fn hash_input(&self, input: &In) -> Self::HashOutput {
+        let mut digest = self.digest();
+        digest.input([0u8]);
+        digest.input(input.as_ref());
+        digest.vec_result()
+    }
+}
+
+impl<D> NodeHasher for VarDigestHasher<D>
+where
+    D: Input + VariableOutput,
+{
+    type HashOutput = Vec<u8>;
+
+    fn hash_children<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        let mut digest = self.digest();
+        digest.input([1u8]);
+        for node in iter {
+            digest.input(node.hash_bytes());
+        }
+        digest.vec_result()
+    }
+
+    /// Returns the digest of empty input, with the configured output size.
+    fn empty_root(&self) -> Self::HashOutput
+    where
+        Self::HashOutput: Default,
+    {
+        self.digest().vec_result()
+    }
+}
+
+impl<D> PairHasher for VarDigestHasher<D>
+where
+    D: Input + VariableOutput,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        _: usize,
+        right: &Self::HashOutput,
+        _: usize,
+    ) -> Self::HashOutput {
+        let mut digest = self.digest();
+        digest.input([1u8]);
+        digest.input(left);
+        digest.input(right);
+        digest.vec_result()
+    }
+}
+
+/// The state of incremental hashing of leaf input with a digest function.
+///
+/// Values of this type are created by the `leaf_hasher()` method of
+/// `ByteDigestHasher`.
+#[derive(Clone, Debug)]
+pub struct LeafDigest<D> {
+    digest: D,
+}
+
+impl<D> LeafHashState for LeafDigest<D>
+where
+    D: Input + FixedOutput,
+{
+    type HashOutput = GenericArray<u8, D::OutputSize>;
+
+    fn update(&mut self, data: &[u8]) {
+        self.digest.input(data);
+    }
+
+    fn finish(self) -> Self::HashOutput {
+        self.digest.fixed_result()
+    }
+}
+
+#[cfg(feature = "sha2")]
+extern crate sha2;
+
+#[cfg(feature = "blake2")]
+extern crate blake2;
+
+/// A hash extractor using SHA-256 with byte input.
+///
+/// This alias is only available if the crate has been compiled with
+/// the `sha2` feature.
+#[cfg(feature = "sha2")]
+pub type Sha256Hasher = ByteDigestHasher<sha2::Sha256>;
+
+/// A hash extractor using SHA-512 with byte input.
+///
+/// This alias is only available if the crate has been compiled with
+/// the `sha2` feature.
+#[cfg(feature = "sha2")]
+pub type Sha512Hasher = ByteDigestHasher<sha2::Sha512>;
+
+/// A hash extractor using BLAKE2b with byte input.
+///
+/// This alias is only available if the crate has been compiled with
+/// the `blake2` feature.
+#[cfg(feature = "blake2")]
+pub type Blake2bHasher = ByteDigestHasher<blake2::Blake2b>;
+
+/// Constructs a `Builder` hashing byte input with SHA-256
+/// and storing no leaf data.
+///
+/// This function is only available if the crate has been compiled with
+/// the `sha2` feature.
+#[cfg(feature = "sha2")]
+pub fn sha256_builder<In>() -> Builder<Sha256Hasher, leaf::NoData<In>>
+where
+    In: AsRef<[u8]>,
+{
+    Builder::new()
+}
+
+/// Constructs a `Builder` hashing byte input with SHA-512
+/// and storing no leaf data.
+///
+/// This function is only available if the crate has been compiled with
+/// the `sha2` feature.
+#[cfg(feature = "sha2")]
+pub fn sha512_builder<In>() -> Builder<Sha512Hasher, leaf::NoData<In>>
+where
+    In: AsRef<[u8]>,
+{
+    Builder::new()
+}
+
+/// Constructs a `Builder` hashing byte input with BLAKE2b
+/// and storing no leaf data.
+///
+/// This function is only available if the crate has been compiled with
+/// the `blake2` feature.
+#[cfg(feature = "blake2")]
+pub fn blake2b_builder<In>() -> Builder<Blake2bHasher, leaf::NoData<In>>
+where
+    In: AsRef<[u8]>,
+{
+    Builder::new()
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{ByteDigestHasher, DigestHasher};
-    use hash::{Hasher, NodeHasher};
+    use super::{ByteDigestHasher, DefaultNodeHasher, DigestHasher};
+    use super::{HeightBindingNodeHasher, TruncatedNodeHasher};
+    use hash::{Hasher, NodeHasher, PairHasher, PassThroughLoneChild};
+    use hash::{IncrementalHasher, LeafHashState};
 
     use leaf;
-    use tree::{Builder, Children};
+    use tree::{Builder, Children, IncrementalRoot, Node};
 
     extern crate sha2;
 
-    use self::sha2::{Digest, Sha256};
+    use self::sha2::{Digest, Sha256, Sha512};
     use super::digest_hash::digest::FixedOutput;
     use super::digest_hash::BigEndian;
+    use super::generic_array::typenum::U32;
     use super::generic_array::GenericArray;
     use std::fmt;
     use std::fmt::Debug;
@@ -369,6 +1289,36 @@ mod tests {
         assert_eq!(hash, leaf_digest(TEST_DATA));
     }
 
+    #[test]
+    fn context_salts_tree() {
+        let builder = Builder::<ByteDigestHasher<Sha256>, _>::new();
+        let plain = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let salted = builder
+            .with_context(&b"salt"[..])
+            .complete_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        let peppered = builder
+            .with_context(&b"pepper"[..])
+            .complete_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        assert_ne!(salted.root().hash(), plain.root().hash());
+        assert_ne!(salted.root().hash(), peppered.root().hash());
+
+        let mut node = salted.root();
+        let leaf_hash = loop {
+            match node {
+                Node::Hash(hn) => node = hn.child_at(0),
+                Node::Leaf(ln) => break ln.hash().clone(),
+            }
+        };
+        let mut digest = Sha256::new();
+        digest.input(&[0u8]);
+        digest.input(4u64.to_be_bytes());
+        digest.input(b"salt");
+        digest.input(&TEST_DATA[..10]);
+        assert_eq!(leaf_hash, digest.result());
+    }
+
     #[test]
     fn hash_endian_input() {
         let hasher = DigestHasher::<BigEndian<Sha256>>::new();
@@ -438,4 +1388,273 @@ mod tests {
             });
         assert_eq!(*tree.root().hash(), root_digest.fixed_result());
     }
+
+    #[test]
+    fn pass_through_lone_child() {
+        type Nh = PassThroughLoneChild<DefaultNodeHasher<Sha256>>;
+        let hasher = ByteDigestHasher::<Sha256, Nh>::new();
+        let builder = Builder::from_hasher_leaf_data(hasher, leaf::no_data());
+        let complete =
+            builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let full = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert_eq!(complete.root().hash(), full.root().hash());
+        let leaf = builder.make_leaf(TEST_DATA);
+        let leaf_hash = *leaf.root().hash();
+        let chain = builder.chain_lone_child(leaf);
+        assert_eq!(*chain.root().hash(), leaf_hash);
+    }
+
+    #[test]
+    fn default_node_hasher_with_arity_binding() {
+        type Nh = DefaultNodeHasher<Sha256>;
+        let hasher = ByteDigestHasher::<Sha256, Nh>::with_node_hasher(
+            Nh::with_arity_binding(true),
+        );
+        let builder = Builder::from_hasher_leaf_data(hasher, leaf::no_data());
+        let leaf = builder.make_leaf(TEST_DATA);
+        let tree = builder.chain_lone_child(leaf);
+        let mut digest = Sha256::new();
+        digest.input([1u8]);
+        digest.input([0, 0, 0, 0, 0, 0, 0, 1]);
+        digest.input(leaf_digest(TEST_DATA).as_slice());
+        assert_eq!(*tree.root().hash(), digest.result());
+    }
+
+    #[test]
+    fn pair_hashing_agrees_with_full_tree() {
+        fn check<Nh>(node_hasher: Nh)
+        where
+            Nh: PairHasher<HashOutput = GenericArray<u8, U32>>,
+            Nh: Clone,
+        {
+            let hasher =
+                ByteDigestHasher::<Sha256, Nh>::with_node_hasher(node_hasher);
+            let builder =
+                Builder::from_hasher_leaf_data(hasher.clone(), leaf::no_data());
+            let chunks: Vec<_> = TEST_DATA.chunks(4).collect();
+            let tree = builder.full_tree_from(&chunks[..]).unwrap();
+            let mut acc = IncrementalRoot::new(hasher);
+            for chunk in &chunks {
+                acc.push(chunk);
+            }
+            assert_eq!(acc.current_root().as_ref(), Some(tree.root().hash()));
+        }
+
+        check(DefaultNodeHasher::<Sha256>::new());
+        check(DefaultNodeHasher::<Sha256>::with_arity_binding(true));
+        check(HeightBindingNodeHasher::<Sha256>::new());
+    }
+
+    #[test]
+    fn height_binding_node_hasher() {
+        type Nh = HeightBindingNodeHasher<Sha256>;
+        let hasher = ByteDigestHasher::<Sha256, Nh>::new();
+        let builder =
+            Builder::from_hasher_leaf_data(hasher.clone(), leaf::no_data());
+        let leaf = builder.make_leaf(TEST_DATA);
+        let chain = builder.chain_lone_child(leaf);
+        let chain = builder.chain_lone_child(chain);
+        let mut digest = Sha256::new();
+        digest.input([1u8]);
+        digest.input([0, 0, 0, 0, 0, 0, 0, 1]);
+        digest.input(leaf_digest(TEST_DATA).as_slice());
+        let inner_hash = digest.result();
+        let mut digest = Sha256::new();
+        digest.input([1u8]);
+        digest.input([0, 0, 0, 0, 0, 0, 0, 2]);
+        digest.input(inner_hash.as_slice());
+        assert_eq!(*chain.root().hash(), digest.result());
+
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(5)).unwrap();
+        if let Node::Hash(ref hn) = *tree.root() {
+            assert!(hn.verify_own_hash(&hasher));
+            assert_eq!(hasher.hash_children(hn.children()), *hn.hash());
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn truncated_hashes() {
+        let hasher = TruncatedNodeHasher::<Sha256, 20>::new();
+        let builder =
+            Builder::from_hasher_leaf_data(hasher.clone(), leaf::no_data());
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let full_leaf_hash = leaf_digest(&TEST_DATA[..10]);
+        let leaf = tree.leaf_at(0).unwrap();
+        assert_eq!(leaf.hash_bytes(), &full_leaf_hash[..20]);
+        if let Node::Hash(ref hn) = *tree.root() {
+            assert_eq!(hn.hash().len(), 20);
+            assert!(hn.verify_own_hash(&hasher));
+        } else {
+            unreachable!()
+        }
+        let empty = Sha256::new().result();
+        assert_eq!(&hasher.empty_root()[..], &empty[..20]);
+        assert_eq!(&NodeHasher::empty_root(&hasher)[..], &empty[..20]);
+
+        let hasher = TruncatedNodeHasher::<Sha512, 48>::new();
+        let empty = Sha512::new().result();
+        assert_eq!(&hasher.empty_root()[..], &empty[..48]);
+    }
+
+    #[cfg(feature = "sha2")]
+    #[test]
+    fn sha256_builder() {
+        let builder = super::sha256_builder();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let expected = Builder::<ByteDigestHasher<Sha256>, _>::new()
+            .complete_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        assert_eq!(tree.root().hash(), expected.root().hash());
+    }
+
+    #[test]
+    fn custom_tags() {
+        let hasher = ByteDigestHasher::<Sha256>::with_tags(
+            b"leaf:".to_vec(),
+            b"node:".to_vec(),
+        );
+        let builder = Builder::from_hasher_leaf_data(hasher, leaf::no_data());
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(25)).unwrap();
+        let leaf_hashes: Vec<_> = TEST_DATA
+            .chunks(25)
+            .map(|chunk| {
+                let mut digest = Sha256::new();
+                digest.input(b"leaf:");
+                digest.input(chunk);
+                digest.result()
+            })
+            .collect();
+        let mut digest = Sha256::new();
+        digest.input(b"node:");
+        digest.input(leaf_hashes[0].as_slice());
+        digest.input(leaf_hashes[1].as_slice());
+        assert_eq!(*tree.root().hash(), digest.result());
+    }
+
+    #[test]
+    fn custom_tag_with_arity_binding() {
+        type Nh = DefaultNodeHasher<Sha256>;
+        let node_hasher = Nh::new().arity_binding(true).tag(&b"node:"[..]);
+        let hasher =
+            ByteDigestHasher::<Sha256, Nh>::with_node_hasher(node_hasher);
+        let builder = Builder::from_hasher_leaf_data(hasher, leaf::no_data());
+        let leaf = builder.make_leaf(TEST_DATA);
+        let tree = builder.chain_lone_child(leaf);
+        let mut digest = Sha256::new();
+        digest.input(b"node:");
+        digest.input([0, 0, 0, 0, 0, 0, 0, 1]);
+        digest.input(leaf_digest(TEST_DATA).as_slice());
+        assert_eq!(*tree.root().hash(), digest.result());
+    }
+
+    #[test]
+    fn empty_leaf_input() {
+        let hasher = ByteDigestHasher::<Sha256>::new();
+        let builder =
+            Builder::from_hasher_leaf_data(hasher.clone(), leaf::no_data());
+        let leaf = builder.make_leaf(&[] as &[u8]);
+        assert_eq!(*leaf.root().hash(), Sha256::digest(&[0u8]));
+        let tree = builder.chain_lone_child(leaf);
+        assert_ne!(tree.root().hash(), tree.leaves().next().unwrap().hash());
+    }
+
+    #[test]
+    fn different_leaf_and_node_digests() {
+        use self::sha2::Sha512Trunc256;
+
+        type Nh = DefaultNodeHasher<Sha512Trunc256>;
+        let builder = Builder::<ByteDigestHasher<Sha256, Nh>, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(32)).unwrap();
+        let mut digest = Sha512Trunc256::new();
+        digest.input([1u8]);
+        digest.input(leaf_digest(&TEST_DATA[..32]));
+        digest.input(leaf_digest(&TEST_DATA[32..]));
+        assert_eq!(*tree.root().hash(), digest.result());
+    }
+
+    #[test]
+    fn cow_input() {
+        use std::borrow::Cow;
+
+        let builder = Builder::<ByteDigestHasher<Sha256>, _>::new();
+        let input: Vec<Cow<[u8]>> = TEST_DATA
+            .chunks(10)
+            .enumerate()
+            .map(|(i, chunk)| {
+                if i % 2 == 0 {
+                    Cow::Borrowed(chunk)
+                } else {
+                    Cow::Owned(chunk.to_vec())
+                }
+            })
+            .collect();
+        let tree = builder.complete_tree_from(input).unwrap();
+        let builder = Builder::<ByteDigestHasher<Sha256>, _>::new();
+        let expected =
+            builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn digest_block_size() {
+        assert_eq!(ByteDigestHasher::<Sha256>::block_size(), 64);
+        assert_eq!(DigestHasher::<BigEndian<Sha256>>::block_size(), 64);
+    }
+
+    #[test]
+    fn empty_root_is_digest_of_empty_input() {
+        let hasher = ByteDigestHasher::<Sha256>::new();
+        assert_eq!(hasher.empty_root(), Sha256::new().result());
+    }
+
+    #[test]
+    fn incremental_leaf_hashing() {
+        let hasher = ByteDigestHasher::<Sha256>::new();
+        let mut state = hasher.leaf_hasher();
+        for chunk in TEST_DATA.chunks(7) {
+            state.update(chunk);
+        }
+        assert_eq!(state.finish(), hasher.hash_input(&TEST_DATA));
+
+        let builder = Builder::from_hasher_leaf_data(hasher, leaf::no_data());
+        let mut state = builder.leaf_hasher();
+        for chunk in TEST_DATA.chunks(7) {
+            state.update(chunk);
+        }
+        let tree = builder.make_leaf_from_state(state, ());
+        assert_eq!(tree, builder.make_leaf(TEST_DATA));
+    }
+
+    #[cfg(feature = "blake2")]
+    #[test]
+    fn var_digest_hasher() {
+        use super::blake2::VarBlake2b;
+        use super::digest_hash::digest::{Input, VariableOutput};
+        use super::VarDigestHasher;
+
+        VarDigestHasher::<VarBlake2b>::new(0).unwrap_err();
+        for &size in &[20, 32] {
+            let hasher = VarDigestHasher::<VarBlake2b>::new(size).unwrap();
+            assert_eq!(hasher.output_size(), size);
+            let builder =
+                Builder::from_hasher_leaf_data(hasher, leaf::no_data());
+            let tree =
+                builder.complete_tree_from(TEST_DATA.chunks(32)).unwrap();
+            let leaf_hash = |data: &[u8]| {
+                let mut digest = VarBlake2b::new(size).unwrap();
+                digest.input([0u8]);
+                digest.input(data);
+                digest.vec_result()
+            };
+            let mut digest = VarBlake2b::new(size).unwrap();
+            digest.input([1u8]);
+            digest.input(leaf_hash(&TEST_DATA[..32]));
+            digest.input(leaf_hash(&TEST_DATA[32..]));
+            let expected = digest.vec_result();
+            assert_eq!(tree.root().hash_bytes(), &expected[..]);
+            assert_eq!(expected.len(), size);
+        }
+    }
 }
@@ -8,7 +8,11 @@
 
 //! The abstraction of the hash algorithm for Merkle trees.
 
-use tree::Children;
+use tree::{Children, Node};
+
+use std::fmt;
+use std::fmt::Debug;
+use std::hash::{BuildHasher, Hash, Hasher as StdHasher};
 
 /// A hash algorithm implementation for a Merkle tree.
 ///
@@ -46,4 +50,482 @@ pub trait NodeHasher {
         &'a self,
         iter: Children<'a, Self::HashOutput, L>,
     ) -> Self::HashOutput;
+
+    /// Hash a sequence of child nodes to produce the hash value of
+    /// a parent node at the given height.
+    ///
+    /// The height of a node is the number of edges on the path from
+    /// the node down to a leaf through the leftmost child nodes. For
+    /// trees with uniform leaf depth, and for the full trees made by
+    /// `Builder::full_tree_from()`, this is the height of the subtree
+    /// rooted at the node. The builders in this crate call this method
+    /// to calculate hash values of the non-leaf nodes, so that hashers
+    /// can bind the level of a node into its hash value.
+    ///
+    /// The default implementation ignores the height and calls
+    /// `hash_children()`.
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        let _ = height;
+        self.hash_children(iter)
+    }
+
+    /// Returns the hash value to be used by applications as the root hash
+    /// of an empty tree.
+    ///
+    /// Empty trees cannot be constructed with this crate, but some
+    /// protocols define a canonical root hash for them.
+    /// The default implementation returns the default value of the hash
+    /// output type, which for byte arrays is all zeroes.
+    fn empty_root(&self) -> Self::HashOutput
+    where
+        Self::HashOutput: Default,
+    {
+        Self::HashOutput::default()
+    }
+}
+
+/// A `NodeHasher` that can calculate the hash value of a parent node
+/// from the hash values of two child nodes alone.
+///
+/// This is needed by `tree::IncrementalRoot`, which only keeps the hash
+/// values of the subtrees it has accumulated. The height of a leaf node
+/// is 0; the height of the parent node is one more than the height of the
+/// left child, which is never less than the height of the right child.
+/// The result must be equal to the value returned by
+/// `hash_children_at_height()` for the two child nodes.
+pub trait PairHasher: NodeHasher {
+    /// Hash the hash values of two child nodes, given with their heights,
+    /// to produce the hash value of their parent node.
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        left_height: usize,
+        right: &Self::HashOutput,
+        right_height: usize,
+    ) -> Self::HashOutput;
+}
+
+/// A hasher that can calculate hash values of leaf nodes incrementally.
+///
+/// This trait is implemented by hashers that can calculate the hash
+/// value of a leaf node over input delivered as a stream of bytes,
+/// without buffering it first. The hash value produced after feeding
+/// all input to the state object returned by `leaf_hasher()` must be
+/// equal to the value of `hash_input()` over the concatenated bytes.
+pub trait IncrementalHasher: NodeHasher {
+    /// The type of the incremental hashing state.
+    type LeafState: LeafHashState<HashOutput = Self::HashOutput>;
+
+    /// Creates the state for hashing a leaf node's input incrementally.
+    fn leaf_hasher(&self) -> Self::LeafState;
+}
+
+/// The state of an incremental calculation of a leaf node's hash value.
+pub trait LeafHashState {
+    /// The output of the hash function.
+    type HashOutput;
+
+    /// Feeds a chunk of input data to the hash calculation.
+    fn update(&mut self, data: &[u8]);
+
+    /// Completes the calculation and returns the hash value.
+    fn finish(self) -> Self::HashOutput;
+}
+
+impl<Nh: ?Sized + NodeHasher> NodeHasher for &Nh {
+    type HashOutput = Nh::HashOutput;
+
+    fn hash_children<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        (**self).hash_children(iter)
+    }
+
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        (**self).hash_children_at_height(iter, height)
+    }
+
+    fn empty_root(&self) -> Self::HashOutput
+    where
+        Self::HashOutput: Default,
+    {
+        (**self).empty_root()
+    }
+}
+
+impl<Nh: ?Sized + PairHasher> PairHasher for &Nh {
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        left_height: usize,
+        right: &Self::HashOutput,
+        right_height: usize,
+    ) -> Self::HashOutput {
+        (**self).hash_pair(left, left_height, right, right_height)
+    }
+}
+
+impl<D: ?Sized, In: ?Sized> Hasher<In> for &D
+where
+    D: Hasher<In>,
+{
+    fn hash_input(&self, input: &In) -> Self::HashOutput {
+        (**self).hash_input(input)
+    }
+}
+
+impl<D: ?Sized + IncrementalHasher> IncrementalHasher for &D {
+    type LeafState = D::LeafState;
+
+    fn leaf_hasher(&self) -> D::LeafState {
+        (**self).leaf_hasher()
+    }
+}
+
+/// A hasher adapter that makes single-child nodes transparent.
+///
+/// When the node hashing method of this adapter is given exactly one
+/// child node, the hash value of the child is returned unchanged as the
+/// hash of the parent. Otherwise, the calculation is delegated to the
+/// wrapped node hasher, as is hashing of the input data if the wrapped
+/// type implements `Hasher`.
+///
+/// With this adapter, a chain of single-child nodes over a subtree has
+/// the same hash value as the subtree itself, so trees built with and
+/// without padding the unpaired rightmost nodes with
+/// `Builder::chain_lone_child()` can have equal root hashes. Note that
+/// this defeats the protection afforded by hashers that do not treat
+/// such trees as equivalent, such as `digest::DefaultNodeHasher`.
+#[derive(Clone, Default)]
+pub struct PassThroughLoneChild<Nh> {
+    inner: Nh,
+}
+
+impl<Nh> PassThroughLoneChild<Nh> {
+    /// Constructs the adapter wrapping the given hasher.
+    pub fn new(inner: Nh) -> Self {
+        PassThroughLoneChild { inner }
+    }
+
+    /// Returns a reference to the wrapped hasher.
+    pub fn inner(&self) -> &Nh {
+        &self.inner
+    }
+}
+
+impl<Nh: Debug> Debug for PassThroughLoneChild<Nh> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_tuple("PassThroughLoneChild")
+            .field(&self.inner)
+            .finish()
+    }
+}
+
+impl<Nh> NodeHasher for PassThroughLoneChild<Nh>
+where
+    Nh: NodeHasher,
+    Nh::HashOutput: Clone,
+{
+    type HashOutput = Nh::HashOutput;
+
+    fn hash_children<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        if iter.len() == 1 {
+            let child = iter.last().unwrap();
+            return child.hash().clone();
+        }
+        self.inner.hash_children(iter)
+    }
+
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        if iter.len() == 1 {
+            let child = iter.last().unwrap();
+            return child.hash().clone();
+        }
+        self.inner.hash_children_at_height(iter, height)
+    }
+
+    fn empty_root(&self) -> Self::HashOutput
+    where
+        Self::HashOutput: Default,
+    {
+        self.inner.empty_root()
+    }
+}
+
+impl<Nh> PairHasher for PassThroughLoneChild<Nh>
+where
+    Nh: PairHasher,
+    Nh::HashOutput: Clone,
+{
+    fn hash_pair(
+        &self,
+        left: &Self::HashOutput,
+        left_height: usize,
+        right: &Self::HashOutput,
+        right_height: usize,
+    ) -> Self::HashOutput {
+        self.inner.hash_pair(left, left_height, right, right_height)
+    }
+}
+
+impl<Nh, In: ?Sized> Hasher<In> for PassThroughLoneChild<Nh>
+where
+    Nh: Hasher<In>,
+    Nh::HashOutput: Clone,
+{
+    fn hash_input(&self, input: &In) -> Self::HashOutput {
+        self.inner.hash_input(input)
+    }
+}
+
+impl<Nh> IncrementalHasher for PassThroughLoneChild<Nh>
+where
+    Nh: IncrementalHasher,
+    Nh::HashOutput: Clone,
+{
+    type LeafState = Nh::LeafState;
+
+    fn leaf_hasher(&self) -> Nh::LeafState {
+        self.inner.leaf_hasher()
+    }
+}
+
+/// An extension of `NodeHasher` for hashers that can incorporate
+/// a context value, such as a per-tree salt, into the hash values of
+/// internal nodes.
+///
+/// The context is provided by the `Contextual` adapter, which is usually
+/// created with `Builder::with_context()`. The default implementation
+/// of the method ignores the context, so an implementation that does not
+/// need the context can be provided with an empty `impl` block.
+pub trait ContextNodeHasher<C: ?Sized>: NodeHasher {
+    /// Hash a sequence of child nodes to produce the hash value of
+    /// a parent node at the given height, using the given context.
+    ///
+    /// The height is as described for
+    /// `NodeHasher::hash_children_at_height()`.
+    /// The default implementation ignores the context and calls
+    /// `hash_children_at_height()`, so the height is still passed to
+    /// hashers that bind it.
+    fn hash_children_with_context<'a, L>(
+        &'a self,
+        context: &C,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        let _ = context;
+        self.hash_children_at_height(iter, height)
+    }
+}
+
+/// An extension of `Hasher` for hashers that can incorporate a context
+/// value into the hash values of leaf nodes.
+///
+/// See the documentation of `ContextNodeHasher` for details.
+pub trait ContextHasher<C: ?Sized, In: ?Sized>:
+    Hasher<In> + ContextNodeHasher<C>
+{
+    /// Hash an element of the input data, using the given context.
+    ///
+    /// The default implementation ignores the context and calls
+    /// `hash_input()`.
+    fn hash_input_with_context(
+        &self,
+        context: &C,
+        input: &In,
+    ) -> Self::HashOutput {
+        let _ = context;
+        self.hash_input(input)
+    }
+}
+
+impl<C: ?Sized, Nh: ?Sized + ContextNodeHasher<C>> ContextNodeHasher<C>
+    for &Nh
+{
+    fn hash_children_with_context<'a, L>(
+        &'a self,
+        context: &C,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        (**self).hash_children_with_context(context, iter, height)
+    }
+}
+
+impl<C, In, D> ContextHasher<C, In> for &D
+where
+    C: ?Sized,
+    In: ?Sized,
+    D: ?Sized + ContextHasher<C, In>,
+{
+    fn hash_input_with_context(
+        &self,
+        context: &C,
+        input: &In,
+    ) -> Self::HashOutput {
+        (**self).hash_input_with_context(context, input)
+    }
+}
+
+/// A hasher adapter that passes a context value to the methods of
+/// `ContextHasher` and `ContextNodeHasher` implemented by the wrapped
+/// hasher.
+///
+/// This allows building many trees with different context values,
+/// such as salts, while reusing a single configured hasher.
+/// Values of this type are usually created with `Builder::with_context()`.
+#[derive(Clone, Debug, Default)]
+pub struct Contextual<D, C> {
+    inner: D,
+    context: C,
+}
+
+impl<D, C> Contextual<D, C> {
+    /// Constructs the adapter wrapping the given hasher and context.
+    pub fn new(inner: D, context: C) -> Self {
+        Contextual { inner, context }
+    }
+
+    /// Returns a reference to the wrapped hasher.
+    pub fn inner(&self) -> &D {
+        &self.inner
+    }
+
+    /// Returns a reference to the context value.
+    pub fn context(&self) -> &C {
+        &self.context
+    }
+}
+
+impl<D, C> NodeHasher for Contextual<D, C>
+where
+    D: ContextNodeHasher<C>,
+{
+    type HashOutput = D::HashOutput;
+
+    // The height of the parent is obtained from the first child node,
+    // whose height is recorded at its creation.
+    fn hash_children<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+    ) -> Self::HashOutput {
+        let height = match iter.as_slice()[0] {
+            Node::Leaf(_) => 1,
+            Node::Hash(ref hn) => hn.height() + 1,
+        };
+        self.inner
+            .hash_children_with_context(&self.context, iter, height)
+    }
+
+    fn hash_children_at_height<'a, L>(
+        &'a self,
+        iter: Children<'a, Self::HashOutput, L>,
+        height: usize,
+    ) -> Self::HashOutput {
+        self.inner
+            .hash_children_with_context(&self.context, iter, height)
+    }
+
+    fn empty_root(&self) -> Self::HashOutput
+    where
+        Self::HashOutput: Default,
+    {
+        self.inner.empty_root()
+    }
+}
+
+impl<D, C, In: ?Sized> Hasher<In> for Contextual<D, C>
+where
+    D: ContextHasher<C, In>,
+{
+    fn hash_input(&self, input: &In) -> Self::HashOutput {
+        self.inner.hash_input_with_context(&self.context, input)
+    }
+}
+
+/// A hasher adapter computing hash values with a `std::hash::Hasher`
+/// implementation, such as `DefaultHasher`.
+///
+/// A new `std::hash::Hasher` is created with the wrapped `BuildHasher`
+/// for every hash value. Leaf hash values are computed over the input
+/// value's `Hash` implementation, internal node hash values over the
+/// hash values of the child nodes. A prefix byte separates the two
+/// kinds of hash values, in the way of RFC 6962.
+///
+/// The 64-bit hash values produced by this adapter are too short to be
+/// collision resistant, and the standard hash functions are not designed
+/// to withstand deliberate collisions. Equality of the root hash values
+/// of two trees therefore does not reliably indicate equality of their
+/// content; this adapter is only suitable for non-security purposes,
+/// such as in-memory deduplication and cache keys.
+/// For the root hash to be reproducible, the `BuildHasher` must be
+/// deterministic: `RandomState` creates differently seeded hashers for
+/// every instance, while `BuildHasherDefault<DefaultHasher>` does not.
+#[derive(Clone, Debug, Default)]
+pub struct StdHasherAdapter<B> {
+    build_hasher: B,
+}
+
+impl<B: BuildHasher> StdHasherAdapter<B> {
+    /// Constructs the adapter using the given `BuildHasher`.
+    pub fn new(build_hasher: B) -> Self {
+        StdHasherAdapter { build_hasher }
+    }
+
+    /// Returns a reference to the wrapped `BuildHasher`.
+    pub fn build_hasher(&self) -> &B {
+        &self.build_hasher
+    }
+}
+
+impl<B: BuildHasher> NodeHasher for StdHasherAdapter<B> {
+    type HashOutput = u64;
+
+    fn hash_children<'a, L>(&'a self, iter: Children<'a, u64, L>) -> u64 {
+        let mut hasher = self.build_hasher.build_hasher();
+        hasher.write_u8(1);
+        for node in iter {
+            hasher.write_u64(*node.hash());
+        }
+        hasher.finish()
+    }
+}
+
+impl<B: BuildHasher> PairHasher for StdHasherAdapter<B> {
+    fn hash_pair(&self, left: &u64, _: usize, right: &u64, _: usize) -> u64 {
+        let mut hasher = self.build_hasher.build_hasher();
+        hasher.write_u8(1);
+        hasher.write_u64(*left);
+        hasher.write_u64(*right);
+        hasher.finish()
+    }
+}
+
+impl<B, In> Hasher<In> for StdHasherAdapter<B>
+where
+    B: BuildHasher,
+    In: ?Sized + Hash,
+{
+    fn hash_input(&self, input: &In) -> u64 {
+        let mut hasher = self.build_hasher.build_hasher();
+        hasher.write_u8(0);
+        input.hash(&mut hasher);
+        hasher.finish()
+    }
 }
@@ -0,0 +1,135 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Human-readable serialization of hash values as hexadecimal strings.
+//!
+//! The functions in this module are meant to be used with the
+//! `serialize_with` and `deserialize_with` field attributes of
+//! `serde_derive`, for hash values represented as `GenericArray` byte
+//! arrays, such as the output of the hashers in module `digest`.
+//!
+//! This module is only available if the crate has been compiled with
+//! the `serialization` feature.
+//!
+//! # Examples
+//!
+//! ```
+//! # extern crate mrkl;
+//! # extern crate generic_array;
+//! # #[macro_use]
+//! # extern crate serde_derive;
+//! # extern crate serde_json;
+//! use generic_array::typenum::U4;
+//! use generic_array::GenericArray;
+//!
+//! #[derive(Serialize, Deserialize)]
+//! struct Checkpoint {
+//!     #[serde(serialize_with = "mrkl::hex::to_hex")]
+//!     #[serde(deserialize_with = "mrkl::hex::from_hex")]
+//!     root: GenericArray<u8, U4>,
+//! }
+//!
+//! # fn main() {
+//! let json = r#"{"root":"deadbeef"}"#;
+//! let checkpoint: Checkpoint = serde_json::from_str(json).unwrap();
+//! assert_eq!(checkpoint.root.as_slice(), &[0xde, 0xad, 0xbe, 0xef]);
+//! assert_eq!(serde_json::to_string(&checkpoint).unwrap(), json);
+//! # }
+//! ```
+
+extern crate generic_array;
+
+use self::generic_array::{ArrayLength, GenericArray};
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::Serializer;
+
+use std::fmt;
+use std::marker::PhantomData;
+
+const HEX_DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+/// Serializes a byte array as a string of lowercase hexadecimal digits.
+pub fn to_hex<N, S>(
+    bytes: &GenericArray<u8, N>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    N: ArrayLength<u8>,
+    S: Serializer,
+{
+    let mut s = String::with_capacity(bytes.len() * 2);
+    for &b in bytes.iter() {
+        s.push(HEX_DIGITS[(b >> 4) as usize] as char);
+        s.push(HEX_DIGITS[(b & 0xf) as usize] as char);
+    }
+    serializer.serialize_str(&s)
+}
+
+/// Deserializes a byte array from a string of hexadecimal digits.
+///
+/// Both lowercase and uppercase digits are accepted.
+///
+/// # Errors
+///
+/// Fails with a descriptive error if the string contains a character
+/// that is not a hexadecimal digit, or if the number of digits does not
+/// match the length of the array.
+pub fn from_hex<'de, N, De>(
+    deserializer: De,
+) -> Result<GenericArray<u8, N>, De::Error>
+where
+    N: ArrayLength<u8>,
+    De: Deserializer<'de>,
+{
+    deserializer.deserialize_str(HexVisitor(PhantomData))
+}
+
+struct HexVisitor<N>(PhantomData<N>);
+
+impl<'de, N> Visitor<'de> for HexVisitor<N>
+where
+    N: ArrayLength<u8>,
+{
+    type Value = GenericArray<u8, N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string of {} hexadecimal digits", N::to_usize() * 2)
+    }
+
+    fn visit_str<E>(self, v: &str) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        let digits = v.as_bytes();
+        if digits.len() != N::to_usize() * 2 {
+            return Err(E::invalid_length(digits.len(), &self));
+        }
+        let mut bytes = GenericArray::default();
+        for (byte, pair) in bytes.iter_mut().zip(digits.chunks(2)) {
+            let hi = hex_digit_value(pair[0]);
+            let lo = hex_digit_value(pair[1]);
+            match (hi, lo) {
+                (Some(hi), Some(lo)) => *byte = hi << 4 | lo,
+                _ => {
+                    return Err(E::invalid_value(de::Unexpected::Str(v), &self))
+                }
+            }
+        }
+        Ok(bytes)
+    }
+}
+
+fn hex_digit_value(c: u8) -> Option<u8> {
+    match c {
+        b'0'..=b'9' => Some(c - b'0'),
+        b'a'..=b'f' => Some(c - b'a' + 10),
+        b'A'..=b'F' => Some(c - b'A' + 10),
+        _ => None,
+    }
+}
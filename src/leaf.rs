@@ -12,6 +12,9 @@
 //! hashing for, but information derived from input may need to be
 //! associated with leaf nodes. The trait `ExtractData` and its
 //! implementations provide versatile ways of retrieving leaf node data.
+//! The trait `ExtractDataMut` generalizes it to extractors that need
+//! to update their state, and the trait `TryExtractData` provides a view
+//! of extractors that can reject their input.
 
 use std::fmt;
 use std::fmt::Debug;
@@ -29,6 +32,77 @@ pub trait ExtractData {
     fn extract_data(&self, input: Self::Input) -> Self::LeafData;
 }
 
+impl<L: ?Sized + ExtractData> ExtractData for &L {
+    type Input = L::Input;
+    type LeafData = L::LeafData;
+    fn extract_data(&self, input: L::Input) -> L::LeafData {
+        (**self).extract_data(input)
+    }
+}
+
+/// A way to extract data for leaf nodes of a Merkle tree, which may
+/// update the state of the extractor.
+///
+/// This trait is implemented for all implementations of `ExtractData`.
+/// Extractors implementing only `ExtractDataMut` can be used to make
+/// leaf nodes with the `make_leaf_mut()` method of `tree::Builder`.
+pub trait ExtractDataMut {
+    /// The type of input data.
+    type Input;
+
+    /// The type of data stored in the leaf nodes.
+    type LeafData;
+
+    /// The extraction method for leaf data.
+    fn extract_data_mut(&mut self, input: Self::Input) -> Self::LeafData;
+}
+
+impl<L: ExtractData> ExtractDataMut for L {
+    type Input = L::Input;
+    type LeafData = L::LeafData;
+    fn extract_data_mut(&mut self, input: L::Input) -> L::LeafData {
+        self.extract_data(input)
+    }
+}
+
+/// A way to extract data for leaf nodes of a Merkle tree, which may
+/// fail on invalid input.
+///
+/// This trait is implemented for all implementations of `ExtractData`
+/// that produce `Result` values, so a fallible extractor can be made
+/// e.g. by passing a function returning `Result` to `extract_with()`.
+/// Such extractors can be used with the `try_make_leaf()` and
+/// `try_complete_tree_from()` methods of `tree::Builder` to build trees
+/// with the successfully extracted values as leaf data.
+pub trait TryExtractData {
+    /// The type of input data.
+    type Input;
+
+    /// The type of data stored in the leaf nodes.
+    type LeafData;
+
+    /// The type of errors returned for rejected input.
+    type Error;
+
+    /// The extraction method for leaf data.
+    fn try_extract_data(
+        &self,
+        input: Self::Input,
+    ) -> Result<Self::LeafData, Self::Error>;
+}
+
+impl<L, T, E> TryExtractData for L
+where
+    L: ExtractData<LeafData = Result<T, E>>,
+{
+    type Input = L::Input;
+    type LeafData = T;
+    type Error = E;
+    fn try_extract_data(&self, input: L::Input) -> Result<T, E> {
+        self.extract_data(input)
+    }
+}
+
 /// Used to build a no-data Merkle tree.
 ///
 /// Trees built with this extractor contain only hashes in their leaf
@@ -150,6 +224,48 @@ where
     }
 }
 
+/// An adapter structure used to fit `FnMut` closures to extract leaf
+/// node data.
+///
+/// This extractor implements `ExtractDataMut`, but not `ExtractData`,
+/// so trees using it can only be built incrementally, making leaf nodes
+/// with the `make_leaf_mut()` method of `tree::Builder`.
+pub struct ExtractFnMut<In, F> {
+    extractor: F,
+    phantom: PhantomData<In>,
+}
+
+impl<In, F> Debug for ExtractFnMut<In, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("ExtractFnMut")
+    }
+}
+
+impl<In, F, Out> ExtractFnMut<In, F>
+where
+    F: FnMut(In) -> Out,
+{
+    /// Create an instance of the extractor wrapping the closure
+    /// passed as the parameter.
+    pub fn with(extractor: F) -> Self {
+        ExtractFnMut {
+            extractor,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<In, F, Out> ExtractDataMut for ExtractFnMut<In, F>
+where
+    F: FnMut(In) -> Out,
+{
+    type Input = In;
+    type LeafData = Out;
+    fn extract_data_mut(&mut self, input: In) -> Out {
+        (self.extractor)(input)
+    }
+}
+
 impl<In, Out> ExtractData for fn(In) -> Out {
     type Input = In;
     type LeafData = Out;
@@ -158,6 +274,43 @@ impl<In, Out> ExtractData for fn(In) -> Out {
     }
 }
 
+/// An adapter for observing the input values consumed by a leaf data
+/// extractor.
+///
+/// The inspecting closure is called with a reference to each input value
+/// before the value is passed to the wrapped extractor. This can be used
+/// to observe the leaf input as the tree is being built, e.g. to feed
+/// a side channel, without implementing `ExtractData` anew.
+///
+/// Instances of this type are created by the function `inspect_with()`.
+/// The adapter is cloneable when both the wrapped extractor and the
+/// closure are, so it can be used with `tree::parallel::Builder`
+/// as long as the closure only captures cloneable, thread-safe values.
+#[derive(Clone)]
+pub struct Inspect<L, F> {
+    extractor: L,
+    inspector: F,
+}
+
+impl<L: Debug, F> Debug for Inspect<L, F> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_tuple("Inspect").field(&self.extractor).finish()
+    }
+}
+
+impl<L, F> ExtractData for Inspect<L, F>
+where
+    L: ExtractData,
+    F: Fn(&L::Input),
+{
+    type Input = L::Input;
+    type LeafData = L::LeafData;
+    fn extract_data(&self, input: L::Input) -> L::LeafData {
+        (self.inspector)(&input);
+        self.extractor.extract_data(input)
+    }
+}
+
 /// A helper function to create instances of `NoData`
 /// with a more concise syntax.
 pub fn no_data<In>() -> NoData<In> {
@@ -183,9 +336,27 @@ pub fn extract_with<In, Out>(extractor: fn(In) -> Out) -> fn(In) -> Out {
     extractor
 }
 
+/// A helper function to wrap a leaf data extractor with an inspecting
+/// closure.
+///
+/// See the documentation on `Inspect` for details.
+pub fn inspect_with<L, F>(extractor: L, inspector: F) -> Inspect<L, F>
+where
+    L: ExtractData,
+    F: Fn(&L::Input),
+{
+    Inspect {
+        extractor,
+        inspector,
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{extract_with, no_data, owned};
+    use super::{extract_with, inspect_with, no_data, owned, ExtractData};
+    use super::{ExtractDataMut, ExtractFnMut};
+
+    use std::cell::RefCell;
 
     #[derive(Debug)]
     struct NonCloneable;
@@ -212,4 +383,30 @@ mod tests {
         });
         let _ = extractor.clone();
     }
+
+    #[test]
+    fn inspect_with_observes_input() {
+        let seen = RefCell::new(Vec::new());
+        let extractor = inspect_with(owned::<u32>(), |input: &u32| {
+            seen.borrow_mut().push(*input)
+        });
+        assert_eq!(extractor.extract_data(1), 1);
+        assert_eq!(extractor.extract_data(2), 2);
+        assert_eq!(*seen.borrow(), [1, 2]);
+    }
+
+    #[test]
+    fn extract_fn_mut_keeps_state() {
+        let mut extractor = ExtractFnMut::with({
+            let mut total = 0;
+            move |len: usize| {
+                total += len;
+                total
+            }
+        });
+        assert_eq!(extractor.extract_data_mut(3), 3);
+        assert_eq!(extractor.extract_data_mut(4), 7);
+        let mut extractor = owned::<u32>();
+        assert_eq!(extractor.extract_data_mut(1), 1);
+    }
 }
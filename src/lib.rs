@@ -12,8 +12,11 @@
 //! data, the hash function, and what gets into leaf data.
 //!
 //! Optional support is provided for the cryptographic hash functions
-//! that conform to the API defined in crate `digest`.
+//! that conform to the API defined in crate `digest`, and for building
+//! Merkle trees as specified in RFC 6962.
 
+#[cfg(feature = "serialization")]
+extern crate serde;
 #[cfg(feature = "serialization")]
 #[macro_use]
 extern crate serde_derive;
@@ -25,4 +28,13 @@ pub mod tree;
 #[cfg(feature = "digest")]
 pub mod digest;
 
+#[cfg(feature = "rfc6962")]
+pub mod rfc6962;
+
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+
+#[cfg(feature = "serialization")]
+pub mod hex;
+
 pub use tree::MerkleTree;
@@ -0,0 +1,160 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Merkle trees as specified for Certificate Transparency.
+//!
+//! This module provides presets for building Merkle trees conformant to
+//! the Merkle Hash Tree definition in [IETF RFC 6962][rfc6962]:
+//! SHA-256 is used as the hash function, a 0 byte is prepended to the
+//! hash input of each leaf node, a 1 byte is prepended to the
+//! concatenation of the two children's hash values, and the tree is
+//! split so that the left subtree of every internal node holds
+//! the largest power of two of leaves that is smaller than the
+//! number of leaves under the node. The latter is the layout produced by
+//! `Builder::full_tree_from()`.
+//!
+//! [rfc6962]: https://tools.ietf.org/html/rfc6962#section-2.1
+//!
+//! This module is only available if the crate has been compiled with
+//! the `rfc6962` feature.
+
+extern crate sha2;
+
+use self::sha2::Sha256;
+use digest::generic_array::GenericArray;
+use digest::ByteDigestHasher;
+use hash::NodeHasher;
+use tree::{BuildError, BuildResult, Builder};
+
+use digest::digest_hash::digest::FixedOutput;
+
+/// The hash extractor type used for RFC 6962 trees.
+pub type Hasher = ByteDigestHasher<Sha256>;
+
+/// The type of hash values in RFC 6962 trees.
+pub type Hash = GenericArray<u8, <Sha256 as FixedOutput>::OutputSize>;
+
+/// Constructs an RFC 6962 Merkle tree from a sequence of input values
+/// with a known length.
+///
+/// # Errors
+///
+/// Returns `BuildError::Empty` when the input sequence is empty.
+/// A tree cannot be constructed for an empty sequence; use `root_from()`
+/// to obtain the hash value defined by RFC 6962 for an empty list.
+pub fn tree_from<I>(iterable: I) -> BuildResult<Hash, ()>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+    I::IntoIter: ExactSizeIterator,
+{
+    let builder = Builder::<Hasher, _>::new();
+    builder.full_tree_from(iterable)
+}
+
+/// Calculates the RFC 6962 Merkle tree hash over a sequence of input
+/// values with a known length.
+///
+/// For an empty sequence, the hash of an empty string is returned,
+/// as specified by RFC 6962.
+///
+/// # Errors
+///
+/// Returns the error that `tree_from()` would return for the same input,
+/// other than `BuildError::Empty`.
+pub fn root_from<I>(iterable: I) -> Result<Hash, BuildError>
+where
+    I: IntoIterator,
+    I::Item: AsRef<[u8]>,
+    I::IntoIter: ExactSizeIterator,
+{
+    match tree_from(iterable) {
+        Ok(tree) => Ok(*tree.root().hash()),
+        Err(BuildError::Empty) => Ok(Hasher::new().empty_root()),
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{root_from, tree_from};
+    use tree::BuildError;
+
+    // Test vectors from the Certificate Transparency reference
+    // implementation.
+    const LEAVES: &'static [&'static [u8]] = &[
+        b"",
+        b"\x00",
+        b"\x10",
+        b"\x20\x21",
+        b"\x30\x31",
+        b"\x40\x41\x42\x43",
+        b"\x50\x51\x52\x53\x54\x55\x56\x57",
+        b"\x60\x61\x62\x63\x64\x65\x66\x67\
+          \x68\x69\x6a\x6b\x6c\x6d\x6e\x6f",
+    ];
+
+    const ROOTS: &'static [&'static str] = &[
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    fn to_hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn test_vector_roots() {
+        for (n, expected) in ROOTS.iter().enumerate() {
+            let root = root_from(&LEAVES[..n + 1]).unwrap();
+            assert_eq!(&to_hex(&root), expected, "{} leaves", n + 1);
+        }
+    }
+
+    #[test]
+    fn empty_tree_root() {
+        let root = root_from(&[] as &[&[u8]]).unwrap();
+        assert_eq!(
+            to_hex(&root),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        tree_from(&[] as &[&[u8]]).unwrap_err();
+    }
+
+    #[test]
+    fn root_from_length_mismatch() {
+        struct Liar<I> {
+            inner: I,
+            reported: usize,
+        }
+
+        impl<I: Iterator> Iterator for Liar<I> {
+            type Item = I::Item;
+            fn next(&mut self) -> Option<I::Item> {
+                self.inner.next()
+            }
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.reported, Some(self.reported))
+            }
+        }
+
+        impl<I: Iterator> ExactSizeIterator for Liar<I> {}
+
+        let liar = Liar {
+            inner: LEAVES.iter(),
+            reported: 3,
+        };
+        assert_eq!(root_from(liar).unwrap_err(), BuildError::LengthMismatch);
+    }
+}
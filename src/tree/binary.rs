@@ -0,0 +1,176 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use hash::Hasher;
+use leaf;
+use tree::{BuildResult, Builder, MerkleTree};
+
+/// A builder restricted to constructing binary Merkle trees.
+///
+/// This is a thin wrapper over `Builder` that only exposes the methods
+/// producing internal nodes with two children, or a lone child.
+/// Use it to have the type system rule out accidental construction
+/// of nodes with a greater number of children.
+#[derive(Clone, Debug, Default)]
+pub struct BinaryBuilder<D, L>
+where
+    D: Hasher<L::Input>,
+    L: leaf::ExtractData,
+{
+    inner: Builder<D, L>,
+}
+
+impl<D, In> BinaryBuilder<D, leaf::NoData<In>>
+where
+    D: Hasher<In> + Default,
+{
+    /// Constructs a `BinaryBuilder` with a default instance of the hash
+    /// extractor, and `NoData` in place of the leaf data extractor.
+    /// The constructed tree will contain only hash values in its leaf nodes.
+    pub fn new() -> Self {
+        BinaryBuilder {
+            inner: Builder::new(),
+        }
+    }
+}
+
+impl<D, L> BinaryBuilder<D, L>
+where
+    D: Hasher<L::Input>,
+    L: leaf::ExtractData,
+{
+    /// Constructs a `BinaryBuilder` from the given instances of the hasher
+    /// and the leaf data extractor.
+    pub fn from_hasher_leaf_data(hasher: D, leaf_data_extractor: L) -> Self {
+        let inner = Builder::from_hasher_leaf_data(hasher, leaf_data_extractor);
+        BinaryBuilder { inner }
+    }
+
+    /// Borrows the unrestricted `Builder` with the same hasher
+    /// and leaf data extractor as this `BinaryBuilder`.
+    pub fn as_builder(&self) -> &Builder<D, L> {
+        &self.inner
+    }
+
+    /// Converts this `BinaryBuilder` into an unrestricted `Builder`
+    /// with the same hasher and leaf data extractor.
+    pub fn into_builder(self) -> Builder<D, L> {
+        self.inner
+    }
+
+    /// Transforms input data into a tree consisting of a single leaf node.
+    ///
+    /// This works like `Builder::make_leaf()`.
+    pub fn make_leaf(
+        &self,
+        input: L::Input,
+    ) -> MerkleTree<D::HashOutput, L::LeafData> {
+        self.inner.make_leaf(input)
+    }
+
+    /// Joins the two given subtrees to produce a tree with a new root node,
+    /// with the passed trees converted to the new root's child nodes.
+    ///
+    /// This works like `Builder::join()`.
+    pub fn join(
+        &self,
+        left: MerkleTree<D::HashOutput, L::LeafData>,
+        right: MerkleTree<D::HashOutput, L::LeafData>,
+    ) -> MerkleTree<D::HashOutput, L::LeafData> {
+        self.inner.join(left, right)
+    }
+
+    /// Constructs a Merkle tree with the passed subtree as the single
+    /// child of the root node.
+    ///
+    /// This works like `Builder::chain_lone_child()`.
+    pub fn chain_lone_child(
+        &self,
+        child: MerkleTree<D::HashOutput, L::LeafData>,
+    ) -> MerkleTree<D::HashOutput, L::LeafData> {
+        self.inner.chain_lone_child(child)
+    }
+
+    /// Constructs a left-filled, same-leaf-depth binary Merkle tree from a
+    /// sequence of input values with a known length.
+    ///
+    /// This works like `Builder::complete_tree_from()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Builder::complete_tree_from()`.
+    pub fn complete_tree_from<I>(
+        &self,
+        iterable: I,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.inner.complete_tree_from(iterable)
+    }
+
+    /// Constructs a full binary Merkle tree from a sequence of input values
+    /// with a known length.
+    ///
+    /// This works like `Builder::full_tree_from()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `Builder::full_tree_from()`.
+    pub fn full_tree_from<I>(
+        &self,
+        iterable: I,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.inner.full_tree_from(iterable)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BinaryBuilder;
+
+    use super::super::testmocks::MockHasher;
+    use tree::Builder;
+
+    const TEST_DATA: &'static [u8] =
+        b"The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn join_and_chain() {
+        let builder = BinaryBuilder::<MockHasher, _>::new();
+        let left = builder.make_leaf(&TEST_DATA[..20]);
+        let right = builder.make_leaf(&TEST_DATA[20..]);
+        let tree = builder.join(left, right);
+        let tree = builder.chain_lone_child(tree);
+        assert_eq!(
+            tree.root().hash_bytes(),
+            b"#(>The quick brown fox >jumps over the lazy dog)"
+        );
+    }
+
+    #[test]
+    fn same_trees_as_builder() {
+        let builder = BinaryBuilder::<MockHasher, _>::new();
+        let expected_builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let expected = expected_builder
+            .complete_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        assert_eq!(tree, expected);
+        let tree = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let expected = expected_builder
+            .full_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        assert_eq!(tree, expected);
+    }
+}
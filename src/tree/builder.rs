@@ -8,16 +8,33 @@
 
 use super::plumbing;
 use super::{Children, HashNode, LeafNode, MerkleTree, Node};
-use hash::Hasher;
+use hash::{ContextHasher, Contextual, Hasher, IncrementalHasher};
+use hash::{LeafHashState, NodeHasher};
 use leaf;
 
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::error::Error;
 use std::fmt;
-use std::fmt::Display;
-use std::iter::IntoIterator;
+use std::fmt::{Debug, Display};
+use std::hash::{BuildHasher, Hash};
+use std::io;
+use std::iter::{FromIterator, IntoIterator};
 
 /// A convenience type alias for the result type used by this crate.
-pub type BuildResult<H, T> = Result<MerkleTree<H, T>, EmptyTree>;
+pub type BuildResult<H, T> = Result<MerkleTree<H, T>, BuildError>;
+
+/// The result type of `Builder::complete_tree_with_leaf_index()`, carrying
+/// the tree and the map of leaf hash values to leaf indices.
+pub type LeafIndexBuildResult<H, T> =
+    Result<(MerkleTree<H, T>, HashMap<H, usize>), BuildError>;
+
+/// The result type of `Builder::update_leaf()`, carrying the unchanged
+/// tree as the error value.
+pub type UpdateResult<H, T> = Result<MerkleTree<H, T>, MerkleTree<H, T>>;
+
+/// The result type of `Builder::try_complete_tree_from()`.
+pub type TryBuildResult<H, T, E> = Result<MerkleTree<H, T>, TryBuildError<E>>;
 
 /// The facility for constructing Merkle trees.
 ///
@@ -39,7 +56,7 @@ pub type BuildResult<H, T> = Result<MerkleTree<H, T>, EmptyTree>;
 pub struct Builder<D, L>
 where
     D: Hasher<L::Input>,
-    L: leaf::ExtractData,
+    L: leaf::ExtractDataMut,
 {
     hasher: D,
     leaf_data_extractor: L,
@@ -64,7 +81,7 @@ where
 impl<D, L> Clone for Builder<D, L>
 where
     D: Hasher<L::Input> + Clone,
-    L: leaf::ExtractData + Clone,
+    L: leaf::ExtractDataMut + Clone,
 {
     fn clone(&self) -> Self {
         Builder::from_hasher_leaf_data(
@@ -114,10 +131,50 @@ where
     }
 }
 
+impl<'h, D, L> Builder<&'h D, &'h L>
+where
+    D: Hasher<L::Input>,
+    L: leaf::ExtractData,
+{
+    /// Constructs a `Builder` that borrows the given instances of the hasher
+    /// and the leaf data extractor.
+    ///
+    /// This is useful when a hasher with a large state is shared by many
+    /// builders, avoiding the need to clone it for each of them.
+    pub fn from_hasher_leaf_data_ref(
+        hasher: &'h D,
+        leaf_data_extractor: &'h L,
+    ) -> Self {
+        Builder::from_hasher_leaf_data(hasher, leaf_data_extractor)
+    }
+}
+
 impl<D, L> Builder<D, L>
 where
     D: Hasher<L::Input>,
     L: leaf::ExtractData,
+{
+    /// Constructs a `Builder` that borrows the hasher and the leaf data
+    /// extractor of this builder, and passes the given context value
+    /// to the hasher's implementation of `ContextHasher`.
+    ///
+    /// This is useful to build many trees with different contexts,
+    /// such as per-tree salts, while reusing a single configured hasher.
+    pub fn with_context<C>(&self, context: C) -> Builder<Contextual<&D, C>, &L>
+    where
+        D: ContextHasher<C, L::Input>,
+    {
+        Builder::from_hasher_leaf_data(
+            Contextual::new(&self.hasher, context),
+            &self.leaf_data_extractor,
+        )
+    }
+}
+
+impl<D, L> Builder<D, L>
+where
+    D: Hasher<L::Input>,
+    L: leaf::ExtractDataMut,
 {
     /// Constructs a `Builder` from the given instances of the hasher
     /// and the leaf data extractor.
@@ -128,17 +185,63 @@ where
         }
     }
 
-    /// Transforms input data into a tree consisting of a single leaf node.
+    /// Creates the state for hashing a leaf node's input incrementally,
+    /// using the hash extractor of this `Builder`.
     ///
-    /// The hash value for the root leaf node is calculated by the hash
-    /// extractor, and the leaf data value is obtained by the leaf data
-    /// extractor used by this `Builder`.
-    pub fn make_leaf(
+    /// The hash value produced by the returned state object is equal to
+    /// the hash of a leaf node made from the concatenated input.
+    pub fn leaf_hasher(&self) -> D::LeafState
+    where
+        D: IncrementalHasher,
+    {
+        self.hasher.leaf_hasher()
+    }
+
+    /// Completes incremental hashing of a leaf node's input and makes
+    /// a tree consisting of a single leaf node with the resulting hash
+    /// value and the given leaf data.
+    ///
+    /// The state object is expected to have been created by
+    /// `leaf_hasher()`. As the input is not available in full, the leaf
+    /// data is not obtained by the leaf data extractor.
+    pub fn make_leaf_from_state(
         &self,
+        state: D::LeafState,
+        data: L::LeafData,
+    ) -> MerkleTree<D::HashOutput, L::LeafData>
+    where
+        D: IncrementalHasher,
+    {
+        MerkleTree {
+            root: Node::Leaf(LeafNode {
+                hash: state.finish(),
+                data,
+            }),
+        }
+    }
+
+    /// Returns a reference to the hash extractor used by this `Builder`.
+    pub fn hasher(&self) -> &D {
+        &self.hasher
+    }
+
+    /// Returns a reference to the leaf data extractor used by this
+    /// `Builder`.
+    pub fn leaf_data_extractor(&self) -> &L {
+        &self.leaf_data_extractor
+    }
+
+    /// Transforms input data into a tree consisting of a single leaf node,
+    /// allowing the leaf data extractor to update its state.
+    ///
+    /// This method can be used with leaf data extractors that only
+    /// implement `leaf::ExtractDataMut`, such as `leaf::ExtractFnMut`.
+    pub fn make_leaf_mut(
+        &mut self,
         input: L::Input,
     ) -> MerkleTree<D::HashOutput, L::LeafData> {
         let hash = self.hasher.hash_input(&input);
-        let data = self.leaf_data_extractor.extract_data(input);
+        let data = self.leaf_data_extractor.extract_data_mut(input);
         MerkleTree {
             root: Node::Leaf(LeafNode { hash, data }),
         }
@@ -149,19 +252,28 @@ where
         children: Box<[Node<D::HashOutput, L::LeafData>]>,
     ) -> BuildResult<D::HashOutput, L::LeafData> {
         if children.is_empty() {
-            return Err(EmptyTree);
+            return Err(BuildError::Empty);
         }
         Ok(self.make_tree_unchecked(children))
     }
 
-    fn make_tree_unchecked(
+    fn make_tree_unchecked<T>(
         &self,
-        children: Box<[Node<D::HashOutput, L::LeafData>]>,
-    ) -> MerkleTree<D::HashOutput, L::LeafData> {
+        children: Box<[Node<D::HashOutput, T>]>,
+    ) -> MerkleTree<D::HashOutput, T> {
         debug_assert!(!children.is_empty());
-        let hash = self.hasher.hash_children(Children(children.iter()));
+        let height = children[0].leftmost_height() + 1;
+        let hash = self
+            .hasher
+            .hash_children_at_height(Children(children.iter()), height);
+        let leaf_count = children.iter().map(Node::leaf_count).sum();
         MerkleTree {
-            root: Node::Hash(HashNode { hash, children }),
+            root: Node::Hash(HashNode {
+                hash,
+                children,
+                leaf_count,
+                height,
+            }),
         }
     }
 
@@ -170,6 +282,13 @@ where
     ///
     /// The `hash_children()` method of the hash extractor is used to obtain
     /// the root hash.
+    ///
+    /// Both subtrees are assumed to have been built with the same hasher
+    /// configuration as this builder; this is not checked, and joining
+    /// subtrees built with differently configured hashers, e.g. with
+    /// different domain separation tags, silently produces a tree that
+    /// no single hasher can verify. Use `join_checked()` to catch such
+    /// misuse.
     pub fn join(
         &self,
         left: MerkleTree<D::HashOutput, L::LeafData>,
@@ -179,6 +298,66 @@ where
         self.make_tree_unchecked(children)
     }
 
+    /// Joins the two given subtrees like `join()`, after checking that
+    /// the hash value of the root of each subtree that is an internal node
+    /// is reproduced by the hasher of this builder.
+    ///
+    /// Only the root hashes are checked, against the hash values of
+    /// their immediate children. Subtrees consisting of a single leaf
+    /// cannot be checked, because the trees do not contain the input
+    /// the leaf hashes were made from.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::HashMismatch` if the root hash of either
+    /// subtree could not be reproduced.
+    ///
+    pub fn join_checked(
+        &self,
+        left: MerkleTree<D::HashOutput, L::LeafData>,
+        right: MerkleTree<D::HashOutput, L::LeafData>,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        D::HashOutput: PartialEq,
+    {
+        for tree in &[&left, &right] {
+            if let Node::Hash(ref hn) = tree.root {
+                if !hn.verify_own_hash(&self.hasher) {
+                    return Err(BuildError::HashMismatch);
+                }
+            }
+        }
+        Ok(self.join(left, right))
+    }
+
+    /// Joins the two given subtrees like `join()`, after checking that
+    /// the subtrees have equal height.
+    ///
+    /// This is a guard for building balanced trees bottom-up, where
+    /// joining subtrees of different heights would silently produce
+    /// a tree with leaves at different depths. The height of a subtree
+    /// is measured along its leftmost path.
+    ///
+    /// # Errors
+    ///
+    /// Returns `HeightMismatch` if the heights of the subtrees differ.
+    ///
+    pub fn join_balanced(
+        &self,
+        left: MerkleTree<D::HashOutput, L::LeafData>,
+        right: MerkleTree<D::HashOutput, L::LeafData>,
+    ) -> Result<MerkleTree<D::HashOutput, L::LeafData>, HeightMismatch> {
+        let left_height = left.root.leftmost_height();
+        let right_height = right.root.leftmost_height();
+        if left_height != right_height {
+            return Err(HeightMismatch {
+                left: left_height,
+                right: right_height,
+            });
+        }
+        Ok(self.join(left, right))
+    }
+
     /// Constructs a Merkle tree with the passed subtree as the single
     /// child of the root node, usually considered to be the leftmost child
     /// in an _n_-ary tree.
@@ -202,9 +381,14 @@ where
     /// The `hash_children()` method of the hash extractor is used to obtain
     /// the root hash.
     ///
+    /// The storage for the child nodes is allocated up front for the number
+    /// of items given by the lower bound of the iterator's `size_hint()`,
+    /// so an iterator reporting its exact length, such as any
+    /// `ExactSizeIterator`, incurs a single allocation.
+    ///
     /// # Errors
     ///
-    /// Returns the `EmptyTree` error when the sequence of nodes is empty.
+    /// Returns `BuildError::Empty` when the sequence of nodes is empty.
     ///
     pub fn collect_children_from<I>(
         &self,
@@ -226,6 +410,28 @@ where
         let children: Vec<_> = iter.map(|tree| tree.root).collect();
         self.make_tree(children.into())
     }
+}
+
+impl<D, L> Builder<D, L>
+where
+    D: Hasher<L::Input>,
+    L: leaf::ExtractData,
+{
+    /// Transforms input data into a tree consisting of a single leaf node.
+    ///
+    /// The hash value for the root leaf node is calculated by the hash
+    /// extractor, and the leaf data value is obtained by the leaf data
+    /// extractor used by this `Builder`.
+    pub fn make_leaf(
+        &self,
+        input: L::Input,
+    ) -> MerkleTree<D::HashOutput, L::LeafData> {
+        let hash = self.hasher.hash_input(&input);
+        let data = self.leaf_data_extractor.extract_data(input);
+        MerkleTree {
+            root: Node::Leaf(LeafNode { hash, data }),
+        }
+    }
 
     /// Constructs a left-filled, same-leaf-depth binary Merkle tree from a
     /// sequence of input values with a known length. The nodes' hashes are
@@ -248,7 +454,12 @@ where
     ///
     /// # Errors
     ///
-    /// Returns the `EmptyTree` error when the input sequence is empty.
+    /// Returns `BuildError::Empty` when the input sequence is empty.
+    /// Returns `BuildError::TooManyLeaves` when the length of the input
+    /// sequence exceeds the largest power of two representable
+    /// by `usize`.
+    /// Returns `BuildError::LengthMismatch` if the iterator produces
+    /// a number of items different from its reported length.
     ///
     /// # Examples
     ///
@@ -286,29 +497,220 @@ where
     where
         I: IntoIterator<Item = L::Input>,
         I::IntoIter: ExactSizeIterator,
+    {
+        self.complete_tree_with(iterable, |input| self.make_leaf(input))
+    }
+
+    /// Constructs the same tree as `complete_tree_from()`, calling the
+    /// given closure with the number of leaf nodes made so far
+    /// after each leaf node is made.
+    ///
+    /// This can be used to report progress of building large trees.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `complete_tree_from()`.
+    pub fn complete_tree_with_progress<I, F>(
+        &self,
+        iterable: I,
+        mut on_leaf: F,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+        F: FnMut(usize),
+    {
+        let mut done = 0;
+        self.complete_tree_with(iterable, |input| {
+            let leaf = self.make_leaf(input);
+            done += 1;
+            on_leaf(done);
+            leaf
+        })
+    }
+
+    /// Constructs the same tree as `complete_tree_from()`, also returning
+    /// a map of the leaf hash values to the indices of the leaves.
+    ///
+    /// The map is filled while the leaf nodes are made, so no additional
+    /// traversal of the tree is needed. It can be used to find whether
+    /// a leaf with a given hash value is in the tree, and at which index.
+    /// If several leaves have the same hash value, the map contains the
+    /// index of the last of them.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `complete_tree_from()`.
+    pub fn complete_tree_with_leaf_index<I>(
+        &self,
+        iterable: I,
+    ) -> LeafIndexBuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+        D::HashOutput: Clone + Eq + Hash,
+    {
+        let mut index = HashMap::new();
+        let mut count = 0;
+        let tree = self.complete_tree_with(iterable, |input| {
+            let leaf = self.make_leaf(input);
+            index.insert(leaf.root().hash().clone(), count);
+            count += 1;
+            leaf
+        })?;
+        Ok((tree, index))
+    }
+
+    /// Constructs the same tree as `complete_tree_from()` from an
+    /// iterable without a known length.
+    ///
+    /// The input items are first collected into a `Vec`, so this method
+    /// temporarily allocates memory for all of the input values.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the input is empty.
+    /// Returns `BuildError::TooManyLeaves` when the length of the input
+    /// exceeds the largest power of two representable by `usize`.
+    pub fn complete_tree_from_unsized<I>(
+        &self,
+        iterable: I,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+    {
+        let inputs: Vec<_> = iterable.into_iter().collect();
+        self.complete_tree_from(inputs)
+    }
+
+    /// Constructs a tree with the layout produced by `complete_tree_from()`
+    /// over consecutive chunks of a byte buffer.
+    ///
+    /// Each leaf is made from a chunk of `chunk_size` bytes, except for
+    /// the last chunk, which is shorter if the length of the data is not
+    /// a multiple of `chunk_size`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the data is empty.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` is 0.
+    pub fn complete_tree_over_chunks<'a>(
+        &self,
+        data: &'a [u8],
+        chunk_size: usize,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        L: leaf::ExtractData<Input = &'a [u8]>,
+    {
+        self.complete_tree_from(data.chunks(chunk_size))
+    }
+
+    /// Transforms input data into a tree consisting of a single leaf node,
+    /// using a fallible leaf data extractor.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the leaf data extractor if it rejects
+    /// the input.
+    pub fn try_make_leaf(
+        &self,
+        input: <L as leaf::ExtractData>::Input,
+    ) -> Result<
+        MerkleTree<D::HashOutput, <L as leaf::TryExtractData>::LeafData>,
+        <L as leaf::TryExtractData>::Error,
+    >
+    where
+        L: leaf::TryExtractData<Input = <L as leaf::ExtractData>::Input>,
+    {
+        let hash = self.hasher.hash_input(&input);
+        let data = self.leaf_data_extractor.try_extract_data(input)?;
+        Ok(MerkleTree {
+            root: Node::Leaf(LeafNode { hash, data }),
+        })
+    }
+
+    /// Constructs the same tree as `complete_tree_from()`, using
+    /// a fallible leaf data extractor.
+    ///
+    /// The leaf nodes are made before the rest of the tree, so that
+    /// extraction errors are detected before any internal node hashes
+    /// are calculated.
+    ///
+    /// # Errors
+    ///
+    /// Returns `TryBuildError::Extract` with the index of the first
+    /// input value rejected by the leaf data extractor, and the error
+    /// returned by the extractor.
+    /// Returns `TryBuildError::Build` for the errors that
+    /// `complete_tree_from()` would return.
+    pub fn try_complete_tree_from<I>(
+        &self,
+        iterable: I,
+    ) -> TryBuildResult<
+        D::HashOutput,
+        <L as leaf::TryExtractData>::LeafData,
+        <L as leaf::TryExtractData>::Error,
+    >
+    where
+        L: leaf::TryExtractData<Input = <L as leaf::ExtractData>::Input>,
+        I: IntoIterator<Item = <L as leaf::ExtractData>::Input>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let leaves = iterable
+            .into_iter()
+            .enumerate()
+            .map(|(index, input)| {
+                self.try_make_leaf(input)
+                    .map_err(|e| TryBuildError::Extract(index, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let tree = self.complete_tree_with(leaves, |leaf| leaf)?;
+        Ok(tree)
+    }
+
+    fn complete_tree_with<I, M, T>(
+        &self,
+        iterable: I,
+        mut make_leaf: M,
+    ) -> BuildResult<D::HashOutput, T>
+    where
+        I: IntoIterator,
+        I::IntoIter: ExactSizeIterator,
+        M: FnMut(I::Item) -> MerkleTree<D::HashOutput, T>,
     {
         let mut iter = iterable.into_iter();
         let len = iter.len();
         if len == 0 {
-            return Err(EmptyTree);
+            return Err(BuildError::Empty);
+        }
+        let perfect_len = len
+            .checked_next_power_of_two()
+            .ok_or(BuildError::TooManyLeaves)?;
+        let tree = self.extract_complete_tree(
+            &mut iter,
+            len,
+            perfect_len,
+            &mut make_leaf,
+        )?;
+        if iter.next().is_some() {
+            return Err(BuildError::LengthMismatch);
         }
-        let perfect_len = len.checked_next_power_of_two().unwrap();
-        let tree = self.extract_complete_tree(&mut iter, len, perfect_len);
-        debug_assert!(
-            iter.next().is_none(),
-            "iterator has not been exhausted after reported length"
-        );
         Ok(tree)
     }
 
-    fn extract_complete_tree<I>(
+    fn extract_complete_tree<I, M, T>(
         &self,
         iter: &mut I,
         len: usize,
         perfect_len: usize,
-    ) -> MerkleTree<D::HashOutput, L::LeafData>
+        make_leaf: &mut M,
+    ) -> BuildResult<D::HashOutput, T>
     where
-        I: Iterator<Item = L::Input>,
+        I: Iterator,
+        M: FnMut(I::Item) -> MerkleTree<D::HashOutput, T>,
     {
         debug_assert!(len != 0);
         let left_len = perfect_len / 2;
@@ -316,117 +718,715 @@ where
             // We're going to have no right subtree on this node.
             // And it's still an internal node because this is never true
             // when perfect_len == 1.
-            let left_tree = self.extract_complete_tree(iter, len, left_len);
-            self.chain_lone_child(left_tree)
+            let left_tree =
+                self.extract_complete_tree(iter, len, left_len, make_leaf)?;
+            Ok(self.make_tree_unchecked(Box::new([left_tree.root])))
         } else if len == 1 {
-            let input = iter.next().expect(
-                "iterator returned None \
-                 before its reported length was reached",
-            );
-            self.make_leaf(input)
+            let input = iter.next().ok_or(BuildError::LengthMismatch)?;
+            Ok(make_leaf(input))
         } else {
-            let left_tree =
-                self.extract_complete_tree(iter, left_len, left_len);
+            let left_tree = self
+                .extract_complete_tree(iter, left_len, left_len, make_leaf)?;
+            // This never overflows or comes to 0 because
+            // left_len < len for len >= 2
+            let right_len = len - left_len;
+            let right_tree = self
+                .extract_complete_tree(iter, right_len, left_len, make_leaf)?;
+            Ok(self.make_tree_unchecked(Box::new([
+                left_tree.root,
+                right_tree.root,
+            ])))
+        }
+    }
+
+    /// Constructs a [full][nist-full] binary Merkle tree from a sequence
+    /// of input values with a known length. The nodes' hashes are calculated
+    /// by the hash extractor, and the leaf data values are extracted from
+    /// input data with the leaf data exractor.
+    ///
+    /// The constructed tree has the following properties: the left subtree
+    /// of any internal node, including root, is a perfect binary tree,
+    /// nodes on every tree level are packed to the left, but the tree is
+    /// not necessarily balanced, i.e. the leaf nodes are not generally all
+    /// on the same level.
+    ///
+    /// [nist-full]: https://xlinux.nist.gov/dads/HTML/fullBinaryTree.html
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the input sequence is empty.
+    /// Returns `BuildError::LengthMismatch` if the iterator produces
+    /// a number of items different from its reported length.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # extern crate mrkl;
+    /// # #[cfg(feature = "digest")]
+    /// # extern crate sha2;
+    /// #
+    /// use mrkl::leaf;
+    /// use mrkl::tree::Builder;
+    /// # #[cfg(feature = "digest")]
+    /// use mrkl::digest::ByteDigestHasher;
+    /// # #[cfg(feature = "digest")]
+    /// use sha2::Sha256;
+    ///
+    /// # #[cfg(feature = "digest")]
+    /// # fn main() {
+    /// type Hasher = ByteDigestHasher<Sha256>;
+    ///
+    /// let builder = Builder::from_hasher_leaf_data(
+    ///                 Hasher::new(),
+    ///                 leaf::extract_with(|s: &[u8]| { s[0] }));
+    /// let input: &'static [u8] = b"The quick brown fox \
+    ///                              jumps over the lazy dog";
+    /// let tree = builder.full_tree_from(input.chunks(10)).unwrap();
+    /// #     let _ = tree;
+    /// # }
+    /// # #[cfg(not(feature = "digest"))]
+    /// # fn main() { }
+    /// ```
+    pub fn full_tree_from<I>(
+        &self,
+        iterable: I,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        let mut iter = iterable.into_iter();
+        let len = iter.len();
+        if len == 0 {
+            return Err(BuildError::Empty);
+        }
+        let tree = self.extract_full_tree(&mut iter, len)?;
+        if iter.next().is_some() {
+            return Err(BuildError::LengthMismatch);
+        }
+        Ok(tree)
+    }
+
+    fn extract_full_tree<I>(
+        &self,
+        iter: &mut I,
+        len: usize,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: Iterator<Item = L::Input>,
+    {
+        debug_assert!(len != 0);
+        if len == 1 {
+            let input = iter.next().ok_or(BuildError::LengthMismatch)?;
+            Ok(self.make_leaf(input))
+        } else {
+            let left_len = (len.saturating_add(1) / 2).next_power_of_two();
+            let left_tree = self.extract_full_tree(iter, left_len)?;
             // This never overflows or comes to 0 because
             // left_len < len for len >= 2
             let right_len = len - left_len;
-            let right_tree =
-                self.extract_complete_tree(iter, right_len, left_len);
-            self.join(left_tree, right_tree)
+            let right_tree = self.extract_full_tree(iter, right_len)?;
+            Ok(self.join(left_tree, right_tree))
+        }
+    }
+
+    /// Constructs a tree with the layout produced by `complete_tree_from()`
+    /// out of a sequence of input values, requiring the keys obtained
+    /// from the input values with the given function to be strictly
+    /// increasing.
+    ///
+    /// This method is intended for sorted-key Merkle trees, where the
+    /// order of leaves needs to be enforced at build time. The keys are
+    /// checked as the input values are consumed, and the construction
+    /// stops at the first value that is out of order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OrderError::Unordered` with the index of the first input
+    /// value whose key is not greater than the key of the preceding value.
+    /// Returns `OrderError::Build` with the error that
+    /// `complete_tree_from()` would return for the same input.
+    pub fn complete_tree_sorted_by<I, F, K>(
+        &self,
+        iterable: I,
+        mut key: F,
+    ) -> Result<MerkleTree<D::HashOutput, L::LeafData>, OrderError>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+        F: FnMut(&L::Input) -> K,
+        K: Ord,
+    {
+        let mut checked = OrderChecked {
+            inner: iterable.into_iter().enumerate(),
+            key: &mut key,
+            prev_key: None,
+            unordered: None,
+        };
+        let result = self.complete_tree_from(&mut checked);
+        if let Some(index) = checked.unordered {
+            return Err(OrderError::Unordered(index));
+        }
+        result.map_err(OrderError::Build)
+    }
+
+    /// Constructs a tree with the layout produced by `complete_tree_from()`
+    /// out of a sequence of input values sorted with the given comparison
+    /// function.
+    ///
+    /// Unlike `complete_tree_sorted_by()`, this method does not require
+    /// the input to be already ordered. The inputs are collected and
+    /// sorted before hashing, so for a fixed set of input values the root
+    /// hash does not depend on the order they are produced in. This is
+    /// intended for content-addressed sets, where the comparison is made
+    /// on a domain key of the elements.
+    ///
+    /// No two input values may compare equal: their relative order after
+    /// sorting would be that of the input, and so would the root hash.
+    ///
+    /// # Errors
+    ///
+    /// Returns `OrderError::Duplicate` with the indices of two input
+    /// values that compare equal. Returns `OrderError::Build` with
+    /// `BuildError::Empty` when the input sequence is empty.
+    pub fn complete_tree_canonical_by<I, F>(
+        &self,
+        iterable: I,
+        mut cmp: F,
+    ) -> Result<MerkleTree<D::HashOutput, L::LeafData>, OrderError>
+    where
+        I: IntoIterator<Item = L::Input>,
+        F: FnMut(&L::Input, &L::Input) -> Ordering,
+    {
+        let mut inputs: Vec<_> = iterable.into_iter().enumerate().collect();
+        inputs.sort_by(|a, b| cmp(&a.1, &b.1));
+        for pair in inputs.windows(2) {
+            if cmp(&pair[0].1, &pair[1].1) == Ordering::Equal {
+                return Err(OrderError::Duplicate(pair[0].0, pair[1].0));
+            }
+        }
+        let tree = self
+            .complete_tree_from(inputs.into_iter().map(|(_, input)| input))?;
+        Ok(tree)
+    }
+
+    /// Constructs a tree with the layout produced by `complete_tree_from()`
+    /// committing to the entries of a hash map.
+    ///
+    /// The entries are sorted by key and each is converted to a leaf
+    /// input value with the function `to_input`. The root hash therefore
+    /// does not depend on the iteration order of the map, which varies
+    /// between map instances with the same content.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the map is empty.
+    pub fn complete_tree_from_map<'a, K, V, S, F>(
+        &self,
+        map: &'a HashMap<K, V, S>,
+        mut to_input: F,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        K: Eq + Hash + Ord,
+        S: BuildHasher,
+        F: FnMut(&'a K, &'a V) -> L::Input,
+    {
+        let mut entries: Vec<_> = map.iter().collect();
+        entries.sort_by(|a, b| a.0.cmp(b.0));
+        self.complete_tree_from(
+            entries.into_iter().map(|(k, v)| to_input(k, v)),
+        )
+    }
+
+    /// Constructs a tree with the layout produced by `complete_tree_from()`
+    /// over a sequence of precomputed leaf hash values.
+    ///
+    /// The leaves are given the hash values in the order they are
+    /// produced and carry no data; only the hash values of the internal
+    /// nodes are calculated. This is intended for commitments to sets,
+    /// where the leaf hash values have been sorted and deduplicated by the
+    /// caller; the order and uniqueness of the values are not checked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the input sequence is empty.
+    /// Returns `BuildError::TooManyLeaves` when the length of the input
+    /// sequence exceeds the largest power of two representable
+    /// by `usize`.
+    /// Returns `BuildError::LengthMismatch` if the iterator produces
+    /// a number of items different from its reported length.
+    pub fn tree_over_sorted_hashes<I>(
+        &self,
+        hashes: I,
+    ) -> BuildResult<D::HashOutput, ()>
+    where
+        I: IntoIterator<Item = D::HashOutput>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        self.complete_tree_with(hashes, |hash| MerkleTree {
+            root: Node::Leaf(LeafNode { hash, data: () }),
+        })
+    }
+
+    /// Constructs a tree with the layout produced by `complete_tree_from()`
+    /// out of the given number of leaves, all made from clones of the
+    /// same input value.
+    ///
+    /// The tree is perfect if the leaf count is a power of two.
+    /// This method is intended as a convenience for benchmarks and tests.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the leaf count is 0.
+    pub fn perfect_tree_of(
+        &self,
+        leaf_count: usize,
+        fill: L::Input,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        L::Input: Clone,
+    {
+        self.complete_tree_from((0..leaf_count).map(|_| fill.clone()))
+    }
+
+    /// Constructs a perfect tree with the given number of leaves out of
+    /// a sequence of input values, padded at the end with clones of
+    /// the filler input value.
+    ///
+    /// This is intended for protocols that fix the size of the tree
+    /// regardless of the amount of data committed to.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::InvalidLeafCount` if the target leaf count is
+    /// not a power of two.
+    /// Returns `BuildError::TooManyLeaves` if the input sequence is
+    /// longer than the target leaf count.
+    pub fn complete_tree_padded_to<I>(
+        &self,
+        iterable: I,
+        target: usize,
+        fill: L::Input,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoIterator<Item = L::Input>,
+        I::IntoIter: ExactSizeIterator,
+        L::Input: Clone,
+    {
+        if !target.is_power_of_two() {
+            return Err(BuildError::InvalidLeafCount);
+        }
+        let mut iter = iterable.into_iter();
+        if iter.len() > target {
+            return Err(BuildError::TooManyLeaves);
+        }
+        self.complete_tree_from(
+            (0..target).map(|_| iter.next().unwrap_or_else(|| fill.clone())),
+        )
+    }
+
+    /// Replaces the leaf at the specified index in the given tree with
+    /// a leaf made from the new input value.
+    ///
+    /// Only the hashes of the nodes on the path from the replaced leaf
+    /// to the root are recalculated; all other subtrees are moved into
+    /// the returned tree unchanged. The index counts leaves in
+    /// left-to-right order, as with `MerkleTree::leaf_at()`.
+    ///
+    /// # Errors
+    ///
+    /// If the index is out of range of the tree's leaves, the tree is
+    /// returned unchanged as the error value.
+    pub fn update_leaf(
+        &self,
+        tree: MerkleTree<D::HashOutput, L::LeafData>,
+        index: usize,
+        input: L::Input,
+    ) -> UpdateResult<D::HashOutput, L::LeafData> {
+        if index >= tree.root.leaf_count() {
+            return Err(tree);
+        }
+        let mut root = tree.root;
+        self.update_leaf_in_node(&mut root, index, input);
+        Ok(MerkleTree { root })
+    }
+
+    fn update_leaf_in_node(
+        &self,
+        node: &mut Node<D::HashOutput, L::LeafData>,
+        index: usize,
+        input: L::Input,
+    ) {
+        match *node {
+            Node::Leaf(_) => {
+                debug_assert!(index == 0);
+            }
+            Node::Hash(ref mut hn) => {
+                let mut index = index;
+                for child in hn.children.iter_mut() {
+                    let count = child.leaf_count();
+                    if index < count {
+                        self.update_leaf_in_node(child, index, input);
+                        break;
+                    }
+                    index -= count;
+                }
+                hn.hash = self.hasher.hash_children_at_height(
+                    Children(hn.children.iter()),
+                    hn.height,
+                );
+                return;
+            }
+        }
+        *node = self.make_leaf(input).root;
+    }
+}
+
+/// Returns the numbers of leaf hash and internal node hash computations
+/// that `Builder::complete_tree_from()` performs for the given number
+/// of leaves, without building a tree.
+///
+/// The first number in the returned pair is the number of calls to
+/// `Hasher::hash_input()`, which equals the leaf count. The second is
+/// the number of calls to `NodeHasher::hash_children_at_height()`, which
+/// equals the number of internal nodes in the complete tree, including
+/// the nodes with a lone child. Both numbers are 0 for a leaf count of 0.
+/// This can be used to estimate the cost of building a tree with an
+/// expensive hash function.
+pub fn hash_op_counts(leaf_count: usize) -> (usize, usize) {
+    let mut node_count = 0;
+    let mut level_len = leaf_count;
+    while level_len > 1 {
+        level_len = level_len / 2 + level_len % 2;
+        node_count += level_len;
+    }
+    (leaf_count, node_count)
+}
+
+/// A tree collected from an iterator over input values.
+///
+/// This type implements `FromIterator`, so that a Merkle tree with no
+/// leaf data can be built with the `collect()` method of any iterator
+/// over input values, using a default instance of the hash extractor
+/// given by the type parameter. The tree is built as if by the
+/// `complete_tree_from()` method of `Builder`. As `FromIterator` cannot
+/// fail, the outcome of the construction is wrapped in this type and
+/// can be retrieved with the `into_result()` method.
+///
+/// # Examples
+///
+/// ```
+/// # extern crate mrkl;
+/// # #[cfg(feature = "digest")]
+/// # extern crate sha2;
+/// #
+/// use mrkl::tree::Collected;
+/// # #[cfg(feature = "digest")]
+/// use mrkl::digest::ByteDigestHasher;
+/// # #[cfg(feature = "digest")]
+/// use sha2::Sha256;
+///
+/// # #[cfg(feature = "digest")]
+/// # fn main() {
+/// type Hasher = ByteDigestHasher<Sha256>;
+///
+/// let input: &'static [u8] = b"The quick brown fox \
+///                              jumps over the lazy dog";
+/// let collected: Collected<Hasher> = input.chunks(10).collect();
+/// let tree = collected.into_result().unwrap();
+/// #     let _ = tree;
+/// # }
+/// # #[cfg(not(feature = "digest"))]
+/// # fn main() { }
+/// ```
+pub struct Collected<D: NodeHasher> {
+    result: BuildResult<D::HashOutput, ()>,
+}
+
+impl<D: NodeHasher> Collected<D> {
+    /// Unwraps the result of building the tree.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BuildError::Empty` when the collected iterator
+    /// was empty.
+    ///
+    pub fn into_result(self) -> BuildResult<D::HashOutput, ()> {
+        self.result
+    }
+}
+
+impl<D, In> FromIterator<In> for Collected<D>
+where
+    D: Hasher<In> + Default,
+{
+    fn from_iter<I>(iterable: I) -> Self
+    where
+        I: IntoIterator<Item = In>,
+    {
+        let input: Vec<_> = iterable.into_iter().collect();
+        let builder = Builder::<D, _>::new();
+        let result = builder.complete_tree_from(input);
+        Collected { result }
+    }
+}
+
+impl<D> Debug for Collected<D>
+where
+    D: NodeHasher,
+    D::HashOutput: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.debug_tuple("Collected").field(&self.result).finish()
+    }
+}
+
+/// The error type for the construction of Merkle trees.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BuildError {
+    /// A tree was attempted to be constructed from empty input.
+    ///
+    /// An empty tree is not considered to be a valid Merkle tree
+    /// by the API of this crate.
+    Empty,
+    /// The input iterator has produced a number of items different from
+    /// the length it had reported.
+    LengthMismatch,
+    /// The number of leaves is too large for the requested tree layout.
+    TooManyLeaves,
+    /// The root hash of a subtree is not consistent with the hasher
+    /// used to build the tree.
+    HashMismatch,
+    /// The requested number of leaves is not valid for the tree layout.
+    InvalidLeafCount,
+}
+
+impl Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BuildError::Empty => {
+                f.write_str("attempted to create an empty Merkle tree")
+            }
+            BuildError::LengthMismatch => f.write_str(
+                "the input iterator did not produce \
+                 the number of items it had reported",
+            ),
+            BuildError::TooManyLeaves => f.write_str(
+                "the number of leaves is too large for the tree layout",
+            ),
+            BuildError::HashMismatch => f.write_str(
+                "the root hash of a subtree does not match the hasher",
+            ),
+            BuildError::InvalidLeafCount => f.write_str(
+                "the requested number of leaves is not valid \
+                 for the tree layout",
+            ),
+        }
+    }
+}
+
+impl Error for BuildError {
+    fn description(&self) -> &str {
+        match *self {
+            BuildError::Empty => "empty Merkle tree",
+            BuildError::LengthMismatch => "input length mismatch",
+            BuildError::TooManyLeaves => "too many leaves",
+            BuildError::HashMismatch => "subtree hash mismatch",
+            BuildError::InvalidLeafCount => "invalid leaf count",
+        }
+    }
+}
+
+// An iterator adapter that ends the iteration at the first input value
+// with a key that is not greater than the key of the preceding value,
+// recording the index of the value. The builder then fails on the
+// iterator running short of its reported length without consuming
+// the rest of the input.
+struct OrderChecked<I, F, K> {
+    inner: I,
+    key: F,
+    prev_key: Option<K>,
+    unordered: Option<usize>,
+}
+
+impl<I, F, K, In> Iterator for OrderChecked<I, F, K>
+where
+    I: Iterator<Item = (usize, In)>,
+    F: FnMut(&In) -> K,
+    K: Ord,
+{
+    type Item = In;
+
+    fn next(&mut self) -> Option<In> {
+        if self.unordered.is_some() {
+            return None;
+        }
+        let (index, input) = self.inner.next()?;
+        let k = (self.key)(&input);
+        if let Some(ref prev) = self.prev_key {
+            if k <= *prev {
+                self.unordered = Some(index);
+                return None;
+            }
+        }
+        self.prev_key = Some(k);
+        Some(input)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I, F, K, In> ExactSizeIterator for OrderChecked<I, F, K>
+where
+    I: ExactSizeIterator<Item = (usize, In)>,
+    F: FnMut(&In) -> K,
+    K: Ord,
+{
+}
+
+/// The error type for the construction of Merkle trees with ordered
+/// leaves.
+///
+/// Errors of this type are returned by
+/// `Builder::complete_tree_sorted_by()` and
+/// `Builder::complete_tree_canonical_by()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum OrderError {
+    /// The key of the input value at the given index is not greater than
+    /// the key of the preceding input value.
+    Unordered(usize),
+    /// The input values at the given indices compare equal, so their
+    /// order in the tree would not be determined by the comparison.
+    Duplicate(usize, usize),
+    /// The tree could not be built for another reason.
+    Build(BuildError),
+}
+
+impl Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            OrderError::Unordered(index) => write!(
+                f,
+                "the key of input value {} is not greater than \
+                 the key of the preceding value",
+                index
+            ),
+            OrderError::Duplicate(first, second) => {
+                write!(f, "input values {} and {} compare equal", first, second)
+            }
+            OrderError::Build(ref e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl Error for OrderError {
+    fn description(&self) -> &str {
+        match *self {
+            OrderError::Unordered(_) => "input values out of order",
+            OrderError::Duplicate(..) => "input values compare equal",
+            OrderError::Build(_) => "Merkle tree build error",
+        }
+    }
+}
+
+impl From<BuildError> for OrderError {
+    fn from(e: BuildError) -> Self {
+        OrderError::Build(e)
+    }
+}
+
+/// The error returned by `Builder::join_balanced()` when the subtrees
+/// to join have different heights.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeightMismatch {
+    left: usize,
+    right: usize,
+}
+
+impl HeightMismatch {
+    /// Returns the height of the left subtree.
+    pub fn left_height(&self) -> usize {
+        self.left
+    }
+
+    /// Returns the height of the right subtree.
+    pub fn right_height(&self) -> usize {
+        self.right
+    }
+}
+
+impl Display for HeightMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "subtrees of different heights {} and {} cannot be joined \
+             in a balanced tree",
+            self.left, self.right
+        )
+    }
+}
+
+impl Error for HeightMismatch {
+    fn description(&self) -> &str {
+        "subtree height mismatch"
+    }
+}
+
+/// The error type for the construction of Merkle trees with a fallible
+/// leaf data extractor.
+///
+/// Errors of this type are returned by
+/// `Builder::try_complete_tree_from()`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TryBuildError<E> {
+    /// The input value at the given index has been rejected by the leaf
+    /// data extractor with the contained error.
+    Extract(usize, E),
+    /// The tree could not be built for another reason.
+    Build(BuildError),
+}
+
+impl<E: Display> Display for TryBuildError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TryBuildError::Extract(index, ref e) => write!(
+                f,
+                "failed to extract leaf data from input value {}: {}",
+                index, e
+            ),
+            TryBuildError::Build(ref e) => Display::fmt(e, f),
+        }
+    }
+}
+
+impl<E: Error> Error for TryBuildError<E> {
+    fn description(&self) -> &str {
+        match *self {
+            TryBuildError::Extract(..) => "failed to extract leaf data",
+            TryBuildError::Build(_) => "Merkle tree build error",
         }
     }
+}
 
-    /// Constructs a [full][nist-full] binary Merkle tree from a sequence
-    /// of input values with a known length. The nodes' hashes are calculated
-    /// by the hash extractor, and the leaf data values are extracted from
-    /// input data with the leaf data exractor.
-    ///
-    /// The constructed tree has the following properties: the left subtree
-    /// of any internal node, including root, is a perfect binary tree,
-    /// nodes on every tree level are packed to the left, but the tree is
-    /// not necessarily balanced, i.e. the leaf nodes are not generally all
-    /// on the same level.
-    ///
-    /// [nist-full]: https://xlinux.nist.gov/dads/HTML/fullBinaryTree.html
-    ///
-    /// # Errors
-    ///
-    /// Returns the `EmptyTree` error when the input sequence is empty.
-    ///
-    /// # Examples
-    ///
-    /// ```
-    /// # extern crate mrkl;
-    /// # #[cfg(feature = "digest")]
-    /// # extern crate sha2;
-    /// #
-    /// use mrkl::leaf;
-    /// use mrkl::tree::Builder;
-    /// # #[cfg(feature = "digest")]
-    /// use mrkl::digest::ByteDigestHasher;
-    /// # #[cfg(feature = "digest")]
-    /// use sha2::Sha256;
-    ///
-    /// # #[cfg(feature = "digest")]
-    /// # fn main() {
-    /// type Hasher = ByteDigestHasher<Sha256>;
-    ///
-    /// let builder = Builder::from_hasher_leaf_data(
-    ///                 Hasher::new(),
-    ///                 leaf::extract_with(|s: &[u8]| { s[0] }));
-    /// let input: &'static [u8] = b"The quick brown fox \
-    ///                              jumps over the lazy dog";
-    /// let tree = builder.full_tree_from(input.chunks(10)).unwrap();
-    /// #     let _ = tree;
-    /// # }
-    /// # #[cfg(not(feature = "digest"))]
-    /// # fn main() { }
-    /// ```
-    pub fn full_tree_from<I>(
-        &self,
-        iterable: I,
-    ) -> BuildResult<D::HashOutput, L::LeafData>
-    where
-        I: IntoIterator<Item = L::Input>,
-        I::IntoIter: ExactSizeIterator,
-    {
-        let mut iter = iterable.into_iter();
-        let len = iter.len();
-        if len == 0 {
-            return Err(EmptyTree);
-        }
-        let tree = self.extract_full_tree(&mut iter, len);
-        debug_assert!(
-            iter.next().is_none(),
-            "iterator has not been exhausted after reported length"
-        );
-        Ok(tree)
+impl<E> From<BuildError> for TryBuildError<E> {
+    fn from(e: BuildError) -> Self {
+        TryBuildError::Build(e)
     }
+}
 
-    fn extract_full_tree<I>(
-        &self,
-        iter: &mut I,
-        len: usize,
-    ) -> MerkleTree<D::HashOutput, L::LeafData>
-    where
-        I: Iterator<Item = L::Input>,
-    {
-        debug_assert!(len != 0);
-        if len == 1 {
-            let input = iter.next().expect(
-                "iterator returned None \
-                 before its reported length was reached",
-            );
-            self.make_leaf(input)
-        } else {
-            let left_len = (len.saturating_add(1) / 2).next_power_of_two();
-            let left_tree = self.extract_full_tree(iter, left_len);
-            // This never overflows or comes to 0 because
-            // left_len < len for len >= 2
-            let right_len = len - left_len;
-            let right_tree = self.extract_full_tree(iter, right_len);
-            self.join(left_tree, right_tree)
-        }
+/// Build errors are converted to I/O errors of kind `InvalidInput`.
+impl From<BuildError> for io::Error {
+    fn from(e: BuildError) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
+#[allow(deprecated)]
+impl From<EmptyTree> for BuildError {
+    fn from(_: EmptyTree) -> Self {
+        BuildError::Empty
     }
 }
 
@@ -435,29 +1435,44 @@ where
 ///
 /// An empty tree is not considered to be a valid Merkle tree
 /// by the API of this crate.
+///
+/// This type is superseded by `BuildError::Empty`; the builder methods
+/// no longer return it.
+#[deprecated(since = "0.0.5", note = "use BuildError::Empty instead")]
 #[derive(Debug)]
 pub struct EmptyTree;
 
+#[allow(deprecated)]
 impl Display for EmptyTree {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         f.write_str("attempted to create an empty Merkle tree")
     }
 }
 
+#[allow(deprecated)]
 impl Error for EmptyTree {
     fn description(&self) -> &str {
         "empty Merkle tree"
     }
 }
 
+/// The error is converted to an I/O error of kind `InvalidInput`.
+#[allow(deprecated)]
+impl From<EmptyTree> for io::Error {
+    fn from(e: EmptyTree) -> Self {
+        io::Error::new(io::ErrorKind::InvalidInput, e)
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Builder;
+    use super::{BuildError, Builder, Collected, OrderError, TryBuildError};
 
     use leaf;
-    use tree::Node;
+    use tree::{HashNode, MerkleTree, Node};
 
     use super::super::testmocks::MockHasher;
+    use std::iter;
 
     const TEST_DATA: &'static [u8] =
         b"The quick brown fox jumps over the lazy dog";
@@ -781,6 +1796,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn complete_tree_with_too_many_leaves() {
+        struct Huge;
+
+        impl Iterator for Huge {
+            type Item = [u8; 1];
+            fn next(&mut self) -> Option<[u8; 1]> {
+                Some([0])
+            }
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (usize::MAX, Some(usize::MAX))
+            }
+        }
+
+        impl ExactSizeIterator for Huge {}
+
+        let builder = Builder::<MockHasher, _>::new();
+        let err = builder.complete_tree_from(Huge).unwrap_err();
+        assert_eq!(err, BuildError::TooManyLeaves);
+    }
+
     #[test]
     fn cant_make_full_from_empty() {
         use std::iter::empty;
@@ -801,4 +1837,516 @@ mod tests {
             unreachable!()
         }
     }
+
+    #[test]
+    fn collect_complete_tree() {
+        let collected: Collected<MockHasher> = TEST_DATA.chunks(15).collect();
+        let tree = collected.into_result().unwrap();
+        let builder = Builder::<MockHasher, _>::new();
+        let expected =
+            builder.complete_tree_from(TEST_DATA.chunks(15)).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn collect_from_empty() {
+        use std::iter::empty;
+        let collected: Collected<MockHasher> = empty::<[u8; 1]>().collect();
+        collected.into_result().unwrap_err();
+    }
+
+    #[test]
+    fn make_leaf_mut_with_stateful_extractor() {
+        let mut offset = 0;
+        let extractor = leaf::ExtractFnMut::with(move |s: &[u8]| {
+            let start = offset;
+            offset += s.len();
+            start
+        });
+        let mut builder =
+            Builder::from_hasher_leaf_data(MockHasher::default(), extractor);
+        let leaves: Vec<_> = TEST_DATA
+            .chunks(10)
+            .map(|chunk| builder.make_leaf_mut(chunk))
+            .collect();
+        let tree = builder.collect_children_from(leaves).unwrap();
+        if let Node::Hash(ref hn) = *tree.root() {
+            let offsets: Vec<_> = hn
+                .children()
+                .map(|child| match *child {
+                    Node::Leaf(ref ln) => *ln.data(),
+                    _ => unreachable!(),
+                })
+                .collect();
+            assert_eq!(offsets, [0, 10, 20, 30, 40]);
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn builder_borrowing_hasher_and_extractor() {
+        use std::ptr;
+
+        let hasher = MockHasher::default();
+        let extractor = leaf::owned();
+        let builder = Builder::from_hasher_leaf_data_ref(&hasher, &extractor);
+        assert!(ptr::eq(*builder.hasher(), &hasher));
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let owning_builder = Builder::from_hasher_leaf_data(
+            MockHasher::default(),
+            leaf::owned(),
+        );
+        let expected = owning_builder
+            .complete_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn complete_tree_sorted_by_key() {
+        let builder = Builder::<MockHasher, _>::new();
+        let input: &[&[u8]] = &[b"ant", b"bee", b"cat", b"dog"];
+        let tree = builder
+            .complete_tree_sorted_by(input, |s| s.to_vec())
+            .unwrap();
+        assert_eq!(tree, builder.complete_tree_from(input).unwrap());
+        let input: &[&[u8]] = &[b"ant", b"bee", b"bee", b"ant"];
+        let err = builder
+            .complete_tree_sorted_by(input, |s| s.to_vec())
+            .unwrap_err();
+        assert_eq!(err, OrderError::Unordered(2));
+        let chunks: Vec<&[u8]> = TEST_DATA.chunks(1).collect();
+        let mut keyed = 0;
+        let err = builder
+            .complete_tree_sorted_by(&chunks[..], |s| {
+                keyed += 1;
+                s.to_vec()
+            })
+            .unwrap_err();
+        assert_eq!(err, OrderError::Unordered(2));
+        assert_eq!(keyed, 3);
+        let input: &[&[u8]] = &[];
+        let err = builder
+            .complete_tree_sorted_by(input, |s| s.to_vec())
+            .unwrap_err();
+        assert_eq!(err, OrderError::Build(BuildError::Empty));
+    }
+
+    #[test]
+    fn complete_tree_with_progress() {
+        let builder = Builder::<MockHasher, _>::new();
+        let mut progress = Vec::new();
+        let tree = builder
+            .complete_tree_with_progress(TEST_DATA.chunks(10), |n| {
+                progress.push(n)
+            })
+            .unwrap();
+        assert_eq!(progress, [1, 2, 3, 4, 5]);
+        let expected =
+            builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn complete_tree_from_unsized() {
+        let builder = Builder::<MockHasher, _>::new();
+        let iter = TEST_DATA.split(|&b| b == b' ');
+        let tree = builder.complete_tree_from_unsized(iter.clone()).unwrap();
+        let inputs: Vec<_> = iter.collect();
+        let expected = builder.complete_tree_from(inputs).unwrap();
+        assert_eq!(tree, expected);
+        let err = builder
+            .complete_tree_from_unsized(iter::empty::<&[u8]>())
+            .unwrap_err();
+        assert_eq!(err, BuildError::Empty);
+    }
+
+    #[test]
+    fn try_complete_tree_from() {
+        fn parse(input: &[u8]) -> Result<u8, usize> {
+            match input {
+                [b] if b.is_ascii_digit() => Ok(b - b'0'),
+                _ => Err(input.len()),
+            }
+        }
+
+        let builder = Builder::from_hasher_leaf_data(
+            MockHasher,
+            leaf::extract_with(parse),
+        );
+        let input: &[&[u8]] = &[b"1", b"2", b"3"];
+        let tree = builder
+            .try_complete_tree_from(input.iter().cloned())
+            .unwrap();
+        let data: Vec<_> = tree.leaves().map(|ln| *ln.data()).collect();
+        assert_eq!(data, [1, 2, 3]);
+        let expected = Builder::<MockHasher, _>::new()
+            .complete_tree_from(input.iter().cloned())
+            .unwrap();
+        assert!(tree.root().hash_matches(expected.root()));
+        let input: &[&[u8]] = &[b"1", b"22", b"3", b"x"];
+        let err = builder
+            .try_complete_tree_from(input.iter().cloned())
+            .unwrap_err();
+        assert_eq!(err, TryBuildError::Extract(1, 2));
+        let err = builder.try_complete_tree_from(iter::empty()).unwrap_err();
+        assert_eq!(err, TryBuildError::Build(BuildError::Empty));
+        builder.try_make_leaf(b"x").unwrap_err();
+    }
+
+    #[test]
+    fn complete_tree_with_leaf_index() {
+        let builder = Builder::<MockHasher, _>::new();
+        let input: &[&[u8]] = &[b"ant", b"bee", b"cat", b"bee"];
+        let (tree, index) = builder
+            .complete_tree_with_leaf_index(input.iter().cloned())
+            .unwrap();
+        let expected =
+            builder.complete_tree_from(input.iter().cloned()).unwrap();
+        assert_eq!(tree, expected);
+        assert_eq!(index.len(), 3);
+        assert_eq!(index[&b"ant"[..]], 0);
+        assert_eq!(index[&b"bee"[..]], 3);
+        assert_eq!(index[&b"cat"[..]], 2);
+    }
+
+    #[test]
+    fn perfect_tree_of_repeated_input() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.perfect_tree_of(4, b"ab").unwrap();
+        assert_eq!(tree.root().hash_bytes(), b"#(>ab>ab)#(>ab>ab)");
+        builder.perfect_tree_of(0, b"ab").unwrap_err();
+    }
+
+    #[test]
+    fn complete_tree_padded_to_target() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder
+            .complete_tree_padded_to(TEST_DATA.chunks(20), 4, b"--")
+            .unwrap();
+        assert_eq!(
+            tree.root().hash_bytes(),
+            &b"#(>The quick brown fox >jumps over the lazy )#(>dog>--)"[..]
+        );
+        assert_eq!(
+            builder
+                .complete_tree_padded_to(TEST_DATA.chunks(20), 6, b"--")
+                .unwrap_err(),
+            BuildError::InvalidLeafCount
+        );
+        assert_eq!(
+            builder
+                .complete_tree_padded_to(TEST_DATA.chunks(20), 2, b"--")
+                .unwrap_err(),
+            BuildError::TooManyLeaves
+        );
+    }
+
+    #[test]
+    fn update_leaf_rehashes_path_to_root() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(7)).unwrap();
+        let tree = builder.update_leaf(tree, 3, b"UMPS OV").unwrap();
+        let mut input = TEST_DATA.to_vec();
+        input[21..28].copy_from_slice(b"UMPS OV");
+        let expected = builder.complete_tree_from(input.chunks(7)).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn update_leaf_out_of_range() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.full_tree_from(TEST_DATA.chunks(7)).unwrap();
+        let tree = builder.update_leaf(tree, 7, b"overrun").unwrap_err();
+        let expected = builder.full_tree_from(TEST_DATA.chunks(7)).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn with_context_salts_leaves() {
+        let builder = Builder::<MockHasher, _>::new();
+        let salted = builder.with_context(&b"salt:"[..]);
+        let tree = salted.complete_tree_from(TEST_DATA.chunks(20)).unwrap();
+        assert_eq!(
+            tree.root().hash_bytes(),
+            &b"#(>salt:The quick brown fox >salt:jumps over the lazy )\
+               #(>salt:dog)"[..]
+        );
+        let other = builder.with_context(&b"pepper:"[..]);
+        let other_tree =
+            other.complete_tree_from(TEST_DATA.chunks(20)).unwrap();
+        assert!(other_tree.root().hash_bytes() != tree.root().hash_bytes());
+    }
+
+    #[test]
+    fn with_context_keeps_height_binding() {
+        use hash::{ContextHasher, ContextNodeHasher, Hasher, NodeHasher};
+        use tree::Children;
+
+        #[derive(Clone, Debug, Default)]
+        struct HeightMock;
+
+        impl<'a> Hasher<&'a [u8]> for HeightMock {
+            fn hash_input(&self, input: &&'a [u8]) -> Vec<u8> {
+                input.to_vec()
+            }
+        }
+
+        impl NodeHasher for HeightMock {
+            type HashOutput = Vec<u8>;
+
+            fn hash_children<'a, L>(
+                &'a self,
+                iter: Children<'a, Vec<u8>, L>,
+            ) -> Vec<u8> {
+                MockHasher.hash_children(iter)
+            }
+
+            fn hash_children_at_height<'a, L>(
+                &'a self,
+                iter: Children<'a, Vec<u8>, L>,
+                height: usize,
+            ) -> Vec<u8> {
+                let mut hash = height.to_string().into_bytes();
+                hash.extend(MockHasher.hash_children(iter));
+                hash
+            }
+        }
+
+        impl ContextNodeHasher<()> for HeightMock {}
+
+        impl ContextHasher<(), &[u8]> for HeightMock {}
+
+        let builder = Builder::<HeightMock, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(20)).unwrap();
+        let with_context = builder
+            .with_context(())
+            .complete_tree_from(TEST_DATA.chunks(20))
+            .unwrap();
+        assert_eq!(with_context.root().hash(), tree.root().hash());
+        assert_eq!(&tree.root().hash_bytes()[..1], b"2");
+    }
+
+    #[test]
+    fn join_checked_detects_foreign_subtree() {
+        let builder = Builder::<MockHasher, _>::new();
+        let left = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let right = builder.make_leaf(b"!");
+        let tree = builder.join_checked(left, right).unwrap();
+        assert_eq!(tree.leaves().count(), 6);
+
+        let children = vec![builder.make_leaf(b"a").root];
+        let left = MerkleTree {
+            root: Node::Hash(HashNode::from_parts(b"bogus".to_vec(), children)),
+        };
+        let right = builder.make_leaf(b"!");
+        assert_eq!(
+            builder.join_checked(left, right).unwrap_err(),
+            BuildError::HashMismatch
+        );
+    }
+
+    #[test]
+    fn complete_tree_over_chunks() {
+        let builder = Builder::<MockHasher, _>::new();
+        for &chunk_size in &[1, 10, 11, TEST_DATA.len(), TEST_DATA.len() + 1] {
+            let tree = builder
+                .complete_tree_over_chunks(TEST_DATA, chunk_size)
+                .unwrap();
+            let expected = builder
+                .complete_tree_from(TEST_DATA.chunks(chunk_size))
+                .unwrap();
+            assert_eq!(tree, expected);
+        }
+        let tree = builder.complete_tree_over_chunks(TEST_DATA, 10).unwrap();
+        assert_eq!(tree.leaves().count(), 5);
+        assert_eq!(tree.leaf_at(4).unwrap().hash_bytes(), b"dog");
+        assert_eq!(
+            builder.complete_tree_over_chunks(&[], 10).unwrap_err(),
+            BuildError::Empty
+        );
+    }
+
+    #[test]
+    fn tree_over_sorted_hashes() {
+        let builder = Builder::<MockHasher, _>::new();
+        let inputs: Vec<&[u8]> = vec![b"ant", b"bee", b"cat", b"dog", b"eel"];
+        let hashes = inputs.iter().map(|s| s.to_vec()).collect::<Vec<_>>();
+        let tree = builder.tree_over_sorted_hashes(hashes).unwrap();
+        let expected = builder.complete_tree_from(inputs).unwrap();
+        assert!(tree.structurally_eq(&expected));
+        assert_eq!(
+            builder
+                .tree_over_sorted_hashes(Vec::<Vec<u8>>::new())
+                .unwrap_err(),
+            BuildError::Empty
+        );
+    }
+
+    #[test]
+    fn complete_tree_from_map() {
+        use std::collections::HashMap;
+
+        let builder = Builder::<MockHasher, _>::new();
+        let mut map = HashMap::new();
+        for &(k, v) in &[(3, "cat"), (1, "ant"), (4, "dog"), (2, "bee")] {
+            map.insert(k, v);
+        }
+        let tree = builder
+            .complete_tree_from_map(&map, |_, v| v.as_bytes())
+            .unwrap();
+        assert_eq!(tree.root().hash_bytes(), &b"#(>ant>bee)#(>cat>dog)"[..]);
+        builder
+            .complete_tree_from_map(&HashMap::<u8, &[u8]>::new(), |_, v| *v)
+            .unwrap_err();
+    }
+
+    #[test]
+    fn complete_tree_canonical_by_ignores_input_order() {
+        let builder = Builder::<MockHasher, _>::new();
+        let inputs: Vec<&[u8]> = vec![b"cat", b"ant", b"dog", b"bee", b"eel"];
+        let tree = builder
+            .complete_tree_canonical_by(inputs.clone(), |a, b| a.cmp(b))
+            .unwrap();
+        let mut shuffled = inputs.clone();
+        shuffled.reverse();
+        shuffled.swap(0, 3);
+        let other = builder
+            .complete_tree_canonical_by(shuffled, |a, b| a.cmp(b))
+            .unwrap();
+        assert_eq!(tree, other);
+        assert_eq!(
+            tree.root().hash_bytes(),
+            &b"#(#(>ant>bee)#(>cat>dog))#(#(>eel))"[..]
+        );
+        let err = builder
+            .complete_tree_canonical_by(Vec::<&[u8]>::new(), |a, b| a.cmp(b))
+            .unwrap_err();
+        assert_eq!(err, OrderError::Build(BuildError::Empty));
+    }
+
+    #[test]
+    fn complete_tree_canonical_by_rejects_key_collision() {
+        let builder = Builder::<MockHasher, _>::new();
+        let inputs: Vec<&[u8]> = vec![b"cat", b"ant", b"cow", b"bee"];
+        let err = builder
+            .complete_tree_canonical_by(inputs, |a, b| a[0].cmp(&b[0]))
+            .unwrap_err();
+        assert_eq!(err, OrderError::Duplicate(0, 2));
+    }
+
+    #[test]
+    fn build_error_into_io_error() {
+        use std::io;
+
+        fn build(input: &[&[u8]]) -> io::Result<()> {
+            let builder = Builder::<MockHasher, _>::new();
+            builder.complete_tree_from(input)?;
+            Ok(())
+        }
+
+        build(&[b"a", b"b"]).unwrap();
+        let err = build(&[]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(err.to_string(), BuildError::Empty.to_string());
+    }
+
+    #[test]
+    fn join_balanced_checks_heights() {
+        let builder = Builder::<MockHasher, _>::new();
+        let left = builder.full_tree_from(TEST_DATA[..8].chunks(2)).unwrap();
+        let right = builder.full_tree_from(TEST_DATA[8..40].chunks(8)).unwrap();
+        let tree = builder.join_balanced(left, right).unwrap();
+        assert!(tree.is_uniform_depth());
+
+        let left = builder.full_tree_from(TEST_DATA[..8].chunks(1)).unwrap();
+        let right = builder.full_tree_from(TEST_DATA[8..40].chunks(8)).unwrap();
+        let err = builder.join_balanced(left, right).unwrap_err();
+        assert_eq!(err.left_height(), 3);
+        assert_eq!(err.right_height(), 2);
+    }
+
+    #[test]
+    fn hash_op_counts_match_build() {
+        use super::hash_op_counts;
+        use hash::{Hasher, NodeHasher};
+        use std::cell::Cell;
+        use tree::Children;
+
+        #[derive(Default)]
+        struct CountingHasher {
+            leaves: Cell<usize>,
+            nodes: Cell<usize>,
+        }
+
+        impl Hasher<u8> for CountingHasher {
+            fn hash_input(&self, input: &u8) -> Vec<u8> {
+                self.leaves.set(self.leaves.get() + 1);
+                MockHasher.hash_input(&[*input])
+            }
+        }
+
+        impl NodeHasher for CountingHasher {
+            type HashOutput = Vec<u8>;
+
+            fn hash_children<'a, L>(
+                &'a self,
+                iter: Children<'a, Vec<u8>, L>,
+            ) -> Vec<u8> {
+                self.nodes.set(self.nodes.get() + 1);
+                MockHasher.hash_children(iter)
+            }
+        }
+
+        assert_eq!(hash_op_counts(0), (0, 0));
+        for n in 1..TEST_DATA.len() + 1 {
+            let hasher = CountingHasher::default();
+            let builder =
+                Builder::from_hasher_leaf_data(&hasher, leaf::no_data());
+            builder.complete_tree_from(TEST_DATA[..n].to_vec()).unwrap();
+            assert_eq!(
+                hash_op_counts(n),
+                (hasher.leaves.get(), hasher.nodes.get()),
+                "{} leaves",
+                n
+            );
+        }
+    }
+
+    #[test]
+    fn iterator_length_mismatch() {
+        struct Liar<I> {
+            inner: I,
+            reported: usize,
+        }
+
+        impl<I: Iterator> Iterator for Liar<I> {
+            type Item = I::Item;
+            fn next(&mut self) -> Option<I::Item> {
+                self.inner.next()
+            }
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (self.reported, Some(self.reported))
+            }
+        }
+
+        impl<I: Iterator> ExactSizeIterator for Liar<I> {}
+
+        let builder = Builder::<MockHasher, _>::new();
+        for &reported in &[3, 6] {
+            let liar = || Liar {
+                inner: TEST_DATA.chunks(10),
+                reported,
+            };
+            assert_eq!(
+                builder.complete_tree_from(liar()).unwrap_err(),
+                BuildError::LengthMismatch
+            );
+            assert_eq!(
+                builder.full_tree_from(liar()).unwrap_err(),
+                BuildError::LengthMismatch
+            );
+        }
+    }
 }
@@ -0,0 +1,109 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use hash::{Hasher, NodeHasher};
+use leaf;
+use tree::{Builder, Children, HashNode, LeafNode, MerkleTree, Node};
+
+use serde::de::{Deserialize, DeserializeSeed, Deserializer, Error};
+
+// These mirror the serialized forms of `Node`, `LeafNode`, and `HashNode`.
+
+#[derive(Deserialize)]
+#[serde(rename = "Node")]
+enum RawNode<H, T> {
+    Leaf(RawLeafNode<H, T>),
+    Hash(RawHashNode<H, T>),
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "LeafNode")]
+struct RawLeafNode<H, T> {
+    hash: H,
+    data: T,
+}
+
+#[derive(Deserialize)]
+#[serde(rename = "HashNode")]
+struct RawHashNode<H, T> {
+    hash: H,
+    children: Vec<RawNode<H, T>>,
+}
+
+/// Deserializes a tree in the format produced by the `Serialize`
+/// implementation of `MerkleTree`, verifying the hash values of the
+/// internal nodes with the hasher of the builder.
+///
+/// The hash value of each internal node is recalculated from the
+/// deserialized hash values of its children, and the deserialization
+/// fails with an error if it differs from the deserialized hash value of
+/// the node, or if an internal node has no children. The hash values
+/// of the leaf nodes cannot be verified, because the tree does not contain
+/// the input the leaf nodes were made from.
+impl<'de, D, L> DeserializeSeed<'de> for &Builder<D, L>
+where
+    D: Hasher<L::Input>,
+    D::HashOutput: Deserialize<'de> + PartialEq,
+    L: leaf::ExtractDataMut,
+    L::LeafData: Deserialize<'de>,
+{
+    type Value = MerkleTree<D::HashOutput, L::LeafData>;
+
+    fn deserialize<De>(self, deserializer: De) -> Result<Self::Value, De::Error>
+    where
+        De: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(rename = "MerkleTree")]
+        struct RawTree<H, T> {
+            root: RawNode<H, T>,
+        }
+
+        let raw = RawTree::deserialize(deserializer)?;
+        let root = verified_node(raw.root, self.hasher())?;
+        Ok(MerkleTree { root })
+    }
+}
+
+fn verified_node<D, T, E>(
+    raw: RawNode<D::HashOutput, T>,
+    hasher: &D,
+) -> Result<Node<D::HashOutput, T>, E>
+where
+    D: NodeHasher,
+    D::HashOutput: PartialEq,
+    E: Error,
+{
+    match raw {
+        RawNode::Leaf(RawLeafNode { hash, data }) => {
+            Ok(Node::Leaf(LeafNode { hash, data }))
+        }
+        RawNode::Hash(RawHashNode { hash, children }) => {
+            if children.is_empty() {
+                return Err(E::custom("internal node has no children"));
+            }
+            let children = children
+                .into_iter()
+                .map(|child| verified_node(child, hasher))
+                .collect::<Result<Box<[_]>, E>>()?;
+            let height = children[0].leftmost_height() + 1;
+            let expected = hasher
+                .hash_children_at_height(Children(children.iter()), height);
+            if hash != expected {
+                return Err(E::custom("hash value of internal node mismatch"));
+            }
+            let leaf_count = children.iter().map(|n| n.leaf_count()).sum();
+            Ok(Node::Hash(HashNode {
+                hash,
+                children,
+                leaf_count,
+                height,
+            }))
+        }
+    }
+}
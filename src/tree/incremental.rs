@@ -0,0 +1,115 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use hash::{Hasher, PairHasher};
+
+/// An accumulator computing the root hash of a growing sequence of
+/// input values, without materializing the tree.
+///
+/// The accumulator keeps the root hashes of the perfect subtrees
+/// covering the input seen so far, at most one per height, merging
+/// two subtrees of equal height as soon as the second one is complete.
+/// `current_root()` combines the subtree roots from right to left,
+/// producing the root hash of the tree that `Builder::full_tree_from()`
+/// would construct out of the same input. Pushing a value takes amortized
+/// constant time and obtaining the root takes logarithmic time in the
+/// number of values pushed.
+///
+/// As the child nodes are not kept, the parent hash values are
+/// calculated with `PairHasher::hash_pair()`.
+#[derive(Debug)]
+pub struct IncrementalRoot<D: PairHasher> {
+    hasher: D,
+    peaks: Vec<(usize, D::HashOutput)>,
+    leaf_count: usize,
+}
+
+impl<D> Default for IncrementalRoot<D>
+where
+    D: PairHasher + Default,
+{
+    fn default() -> Self {
+        IncrementalRoot::new(D::default())
+    }
+}
+
+impl<D: PairHasher> IncrementalRoot<D> {
+    /// Constructs an empty accumulator using the given hasher.
+    pub fn new(hasher: D) -> Self {
+        IncrementalRoot {
+            hasher,
+            peaks: Vec::new(),
+            leaf_count: 0,
+        }
+    }
+
+    /// Returns the number of input values pushed so far.
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    /// Hashes an input value as a new leaf appended to the sequence.
+    pub fn push<In: ?Sized>(&mut self, input: &In)
+    where
+        D: Hasher<In>,
+    {
+        let mut hash = self.hasher.hash_input(input);
+        let mut height = 0;
+        while let Some(&(peak_height, _)) = self.peaks.last() {
+            if peak_height != height {
+                break;
+            }
+            let (_, left) = self.peaks.pop().unwrap();
+            hash = self.hasher.hash_pair(&left, height, &hash, height);
+            height += 1;
+        }
+        self.peaks.push((height, hash));
+        self.leaf_count += 1;
+    }
+
+    /// Returns the root hash of the tree made of all input values pushed
+    /// so far, or `None` if nothing has been pushed.
+    pub fn current_root(&self) -> Option<D::HashOutput>
+    where
+        D::HashOutput: Clone,
+    {
+        let mut peaks = self.peaks.iter().rev();
+        let &(mut root_height, ref root) = peaks.next()?;
+        let mut root = root.clone();
+        for &(height, ref left) in peaks {
+            root = self.hasher.hash_pair(left, height, &root, root_height);
+            root_height = height + 1;
+        }
+        Some(root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IncrementalRoot;
+
+    use super::super::testmocks::MockHasher;
+    use tree::Builder;
+
+    const TEST_DATA: &'static [u8] =
+        b"The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn root_agrees_with_full_tree() {
+        let builder = Builder::<MockHasher, _>::new();
+        let chunks: Vec<_> = TEST_DATA.chunks(3).collect();
+        let mut acc = IncrementalRoot::<MockHasher>::default();
+        assert!(acc.current_root().is_none());
+        for (i, chunk) in chunks.iter().enumerate() {
+            acc.push(chunk);
+            assert_eq!(acc.leaf_count(), i + 1);
+            let tree = builder.full_tree_from(&chunks[..i + 1]).unwrap();
+            assert_eq!(acc.current_root().as_ref(), Some(tree.root().hash()));
+        }
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2017 Mikhail Zabaluev <mikhail.zabaluev@gmail.com>
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use tree::{LeafNode, MerkleTree, Node};
+
+use std::ops::Range;
+
+/// An index over the leaves of a borrowed Merkle tree.
+///
+/// The index records, for every leaf, the path of nodes from the root
+/// of the tree down to the leaf. Once built, it provides lookups of
+/// leaves and their paths by leaf index without descending the tree.
+/// This is useful for workloads that repeatedly look up many leaves in
+/// the same tree, at the cost of memory proportional to the number
+/// of leaves times the depth of the tree.
+#[derive(Debug)]
+pub struct MerkleIndex<'a, H: 'a, T: 'a> {
+    nodes: Vec<&'a Node<H, T>>,
+    paths: Vec<Range<usize>>,
+}
+
+impl<'a, H, T> Clone for MerkleIndex<'a, H, T> {
+    fn clone(&self) -> Self {
+        MerkleIndex {
+            nodes: self.nodes.clone(),
+            paths: self.paths.clone(),
+        }
+    }
+}
+
+impl<'a, H, T> MerkleIndex<'a, H, T> {
+    /// Builds the index for the given tree.
+    ///
+    /// The tree is traversed once; the time taken is proportional to the
+    /// number of leaves times the depth of the tree.
+    pub fn new(tree: &'a MerkleTree<H, T>) -> Self {
+        let mut nodes = Vec::new();
+        let mut paths = Vec::new();
+        let mut path = Vec::new();
+        let mut stack = vec![(tree.root(), 0)];
+        while let Some((node, depth)) = stack.pop() {
+            path.truncate(depth);
+            path.push(node);
+            match *node {
+                Node::Leaf(_) => {
+                    let start = nodes.len();
+                    nodes.extend_from_slice(&path);
+                    paths.push(start..nodes.len());
+                }
+                Node::Hash(ref hn) => {
+                    stack.extend(hn.children().rev().map(|n| (n, depth + 1)));
+                }
+            }
+        }
+        MerkleIndex { nodes, paths }
+    }
+
+    /// Returns the number of leaves in the indexed tree.
+    pub fn leaf_count(&self) -> usize {
+        self.paths.len()
+    }
+
+    /// Returns the nodes on the path from the root of the tree down
+    /// to the leaf at the specified index, including both the root
+    /// and the leaf.
+    ///
+    /// Leaves are counted in left-to-right order, as with
+    /// `MerkleTree::leaf_at()`. Returns `None` if the index is out of range.
+    pub fn path_to(&self, index: usize) -> Option<&[&'a Node<H, T>]> {
+        self.paths
+            .get(index)
+            .map(|range| &self.nodes[range.clone()])
+    }
+
+    /// Borrows the leaf node at the specified index in constant time.
+    ///
+    /// Returns `None` if the index is out of range.
+    pub fn leaf_at(&self, index: usize) -> Option<&'a LeafNode<H, T>> {
+        self.path_to(index)
+            .map(|path| match **path.last().unwrap() {
+                Node::Leaf(ref ln) => ln,
+                Node::Hash(_) => unreachable!(),
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MerkleIndex;
+
+    use super::super::testmocks::MockHasher;
+    use tree::{Builder, Node};
+
+    const TEST_DATA: &'static [u8] =
+        b"The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn index_agrees_with_tree() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let index = MerkleIndex::new(&tree);
+        assert_eq!(index.leaf_count(), 5);
+        for i in 0..5 {
+            let leaf = index.leaf_at(i).unwrap();
+            assert_eq!(leaf, tree.leaf_at(i).unwrap());
+            let path = index.path_to(i).unwrap();
+            assert_eq!(*path[0], *tree.root());
+            for pair in path.windows(2) {
+                if let Node::Hash(ref hn) = *pair[0] {
+                    assert!(hn.children().any(|child| child == pair[1]));
+                } else {
+                    unreachable!()
+                }
+            }
+        }
+        assert_eq!(index.path_to(4).unwrap().len(), 2);
+        assert_eq!(index.path_to(0).unwrap().len(), 4);
+        assert!(index.leaf_at(5).is_none());
+        assert!(index.path_to(5).is_none());
+    }
+}
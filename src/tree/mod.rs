@@ -21,20 +21,39 @@
 //! poorly, incorrect results may occur. Also note that leaf data never
 //! figure in hashing or equality comparisons.
 
+mod binary;
 mod builder;
-pub use self::builder::{BuildResult, Builder, EmptyTree};
+#[cfg(feature = "serialization")]
+mod deserialize;
+mod incremental;
+mod index;
+pub use self::binary::BinaryBuilder;
+pub use self::builder::hash_op_counts;
+#[allow(deprecated)]
+pub use self::builder::EmptyTree;
+pub use self::builder::UpdateResult;
+pub use self::builder::{BuildError, BuildResult, Builder, Collected};
+pub use self::builder::{HeightMismatch, LeafIndexBuildResult};
+pub use self::builder::{OrderError, TryBuildError, TryBuildResult};
+pub use self::incremental::IncrementalRoot;
+pub use self::index::MerkleIndex;
 
 #[cfg(feature = "parallel")]
 pub mod parallel;
 
 mod plumbing;
 
+use hash::{Hasher, NodeHasher};
+
 #[cfg(test)]
 mod testmocks;
 
+use std::convert::TryFrom;
+use std::error::Error;
 use std::fmt;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
 use std::hash as std_hash;
+use std::iter;
 use std::iter::{DoubleEndedIterator, ExactSizeIterator, Iterator};
 use std::slice;
 
@@ -83,6 +102,10 @@ pub struct LeafNode<H, T> {
 pub struct HashNode<H, T> {
     hash: H,
     children: Box<[Node<H, T>]>,
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    leaf_count: usize,
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    height: usize,
 }
 
 impl<H, T> MerkleTree<H, T> {
@@ -90,6 +113,481 @@ impl<H, T> MerkleTree<H, T> {
     pub fn root(&self) -> &Node<H, T> {
         &self.root
     }
+
+    /// Consumes the tree, returning its root node.
+    ///
+    /// This is the owning counterpart of `root()`.
+    pub fn into_root(self) -> Node<H, T> {
+        self.root
+    }
+
+    /// Computes a value over the tree bottom-up.
+    ///
+    /// The `leaf_fn` closure is called for every leaf node to produce
+    /// its value. For every internal node, the `hash_fn` closure is called
+    /// with the node and the values computed for its children in
+    /// left-to-right order. The value computed for the root node is
+    /// returned. This can be used to calculate aggregates over the tree,
+    /// such as sums over the leaf data.
+    ///
+    /// The traversal is implemented iteratively, so deep trees do not
+    /// exhaust the stack.
+    pub fn fold<A, FL, FH>(&self, mut leaf_fn: FL, mut hash_fn: FH) -> A
+    where
+        FL: FnMut(&LeafNode<H, T>) -> A,
+        FH: FnMut(&HashNode<H, T>, Vec<A>) -> A,
+    {
+        let mut values = Vec::new();
+        let mut stack = Vec::new();
+        match self.root {
+            Node::Leaf(ref ln) => return leaf_fn(ln),
+            Node::Hash(ref hn) => stack.push((hn, hn.children())),
+        }
+        while let Some(&mut (hn, ref mut children)) = stack.last_mut() {
+            match children.next() {
+                Some(Node::Leaf(ln)) => values.push(leaf_fn(ln)),
+                Some(Node::Hash(child)) => {
+                    stack.push((child, child.children()));
+                }
+                None => {
+                    let start = values.len() - hn.child_count();
+                    let child_values = values.split_off(start);
+                    values.push(hash_fn(hn, child_values));
+                    stack.pop();
+                }
+            }
+        }
+        debug_assert_eq!(values.len(), 1);
+        values.pop().unwrap()
+    }
+
+    /// Checks whether all leaf nodes of the tree are at the same depth.
+    ///
+    /// This is always true for trees built with
+    /// `Builder::complete_tree_from()`, but a tree assembled by other
+    /// means, such as `Builder::join()`, may not have this property.
+    pub fn is_uniform_depth(&self) -> bool {
+        self.root.uniform_height().is_some()
+    }
+
+    /// Checks whether the internal nodes of the tree have a uniform
+    /// number of children, returning the number if so.
+    ///
+    /// The arity of the tree is taken to be the largest number of
+    /// children among its internal nodes. The nodes on the rightmost path
+    /// of the tree, that is, the root and the last child of every internal
+    /// node on the path, are allowed to have any number of children
+    /// up to the arity, as are the ragged nodes on the right edge of
+    /// the trees made by `Builder::complete_tree_from()`, including lone
+    /// child nodes. Every other internal node must have exactly as many
+    /// children as the arity.
+    ///
+    /// Returns `None` if some internal node off the rightmost path
+    /// has fewer children than the arity, or if the tree consists of
+    /// a single leaf node.
+    pub fn is_uniform_arity(&self) -> Option<usize> {
+        let arity = self.hash_nodes().map(|hn| hn.child_count()).max()?;
+        let mut stack = vec![(&self.root, true)];
+        while let Some((node, rightmost)) = stack.pop() {
+            if let Node::Hash(ref hn) = *node {
+                let count = hn.child_count();
+                if !rightmost && count != arity {
+                    return None;
+                }
+                stack.extend(
+                    hn.children()
+                        .enumerate()
+                        .map(|(i, child)| (child, rightmost && i == count - 1)),
+                );
+            }
+        }
+        Some(arity)
+    }
+
+    /// Returns an iterator over the leaf nodes of the tree,
+    /// in left-to-right order.
+    pub fn leaves<'a>(&'a self) -> Leaves<'a, H, T> {
+        Leaves {
+            stack: vec![Children(slice::from_ref(&self.root).iter())],
+        }
+    }
+
+    /// Returns an iterator over the internal nodes of the tree,
+    /// in preorder.
+    ///
+    /// The iterator yields nothing if the tree consists of a single
+    /// leaf node.
+    pub fn hash_nodes<'a>(&'a self) -> HashNodes<'a, H, T> {
+        HashNodes {
+            stack: vec![Children(slice::from_ref(&self.root).iter())],
+        }
+    }
+
+    /// Returns an iterator over the leaf nodes of the tree paired with
+    /// their indices, in left-to-right order.
+    ///
+    /// The index paired with each leaf is the same index that locates
+    /// the leaf with `leaf_at()`, `siblings_at()`, and
+    /// `MerkleIndex::leaf_at()`.
+    pub fn indexed_leaves<'a>(&'a self) -> iter::Enumerate<Leaves<'a, H, T>> {
+        self.leaves().enumerate()
+    }
+
+    /// Collects references to the data of all leaves of the tree,
+    /// in left-to-right order.
+    pub fn collect_leaf_data(&self) -> Vec<&T> {
+        self.leaves().map(|ln| ln.data()).collect()
+    }
+
+    /// Collects references to the hash values of all leaves of the tree,
+    /// in left-to-right order.
+    pub fn collect_leaf_hashes(&self) -> Vec<&H> {
+        self.leaves().map(|ln| ln.hash()).collect()
+    }
+
+    /// Borrows the leaf node at the specified index, counting leaves
+    /// in left-to-right order as they are visited by `leaves()`.
+    ///
+    /// The leaf is located by descending from the root, using the number
+    /// of leaves under each internal node to choose the child subtree.
+    /// The time taken is proportional to the depth of the leaf times
+    /// the arity of the nodes on the path.
+    ///
+    /// Returns `None` if the index is out of range.
+    pub fn leaf_at(&self, index: usize) -> Option<&LeafNode<H, T>> {
+        if index >= self.root.leaf_count() {
+            return None;
+        }
+        let mut node = &self.root;
+        let mut index = index;
+        loop {
+            match *node {
+                Node::Leaf(ref ln) => return Some(ln),
+                Node::Hash(ref hn) => {
+                    for child in hn.children() {
+                        let count = child.leaf_count();
+                        if index < count {
+                            node = child;
+                            break;
+                        }
+                        index -= count;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the hash values of the siblings of an ancestor node
+    /// of the leaf at the specified index.
+    ///
+    /// The ancestor is identified by its level above the leaf, level 0
+    /// being the leaf itself. The siblings are the other child nodes of
+    /// the ancestor's parent, listed in left-to-right order; if the
+    /// ancestor is a lone child, the returned vector is empty.
+    ///
+    /// Returns `None` if the index is out of range, or if the level
+    /// is not below the root.
+    pub fn siblings_at(&self, index: usize, level: usize) -> Option<Vec<&H>> {
+        if index >= self.root.leaf_count() {
+            return None;
+        }
+        let mut path = Vec::new();
+        let mut node = &self.root;
+        let mut index = index;
+        while let Node::Hash(ref hn) = *node {
+            for (pos, child) in hn.children().enumerate() {
+                let count = child.leaf_count();
+                if index < count {
+                    path.push((hn, pos));
+                    node = child;
+                    break;
+                }
+                index -= count;
+            }
+        }
+        if level >= path.len() {
+            return None;
+        }
+        let (parent, pos) = path[path.len() - 1 - level];
+        let siblings = parent
+            .children()
+            .enumerate()
+            .filter(|&(i, _)| i != pos)
+            .map(|(_, child)| child.hash())
+            .collect();
+        Some(siblings)
+    }
+}
+
+impl<H: Clone, T> MerkleTree<H, T> {
+    /// Returns the hash values of the tree's nodes grouped by level,
+    /// ordered from the deepest level up to the root.
+    ///
+    /// Each level lists the hash values of all nodes at the same depth
+    /// in left-to-right order. The last level contains only the root hash.
+    /// In trees that do not have uniform leaf depth, such as those built
+    /// with `Builder::full_tree_from()`, leaf nodes above the deepest level
+    /// are listed along with the internal nodes at the same depth.
+    pub fn to_levels(&self) -> Vec<Vec<H>> {
+        let mut levels = Vec::new();
+        let mut level = vec![&self.root];
+        while !level.is_empty() {
+            levels.push(level.iter().map(|node| node.hash().clone()).collect());
+            let mut next = Vec::new();
+            for node in level {
+                if let Node::Hash(ref hn) = *node {
+                    next.extend(hn.children());
+                }
+            }
+            level = next;
+        }
+        levels.reverse();
+        levels
+    }
+}
+
+impl<H, T: Clone> MerkleTree<H, T> {
+    /// Constructs a tree with the same structure and leaf data as this tree,
+    /// with the hash values recalculated by another hash extractor.
+    ///
+    /// Since the tree does not retain the input that its leaf nodes
+    /// were made from, the `leaf_input` closure is called for each leaf
+    /// node in left-to-right order to provide the input for hashing
+    /// the new leaf. It can, for example, convert the leaf data back
+    /// into input values. The hash values of the internal nodes are then
+    /// calculated bottom-up by the new hash extractor, which can be
+    /// used to migrate a tree to a different hash function.
+    pub fn rehash_with<D, In, F>(
+        &self,
+        hasher: &D,
+        mut leaf_input: F,
+    ) -> MerkleTree<D::HashOutput, T>
+    where
+        D: Hasher<In>,
+        F: FnMut(&LeafNode<H, T>) -> In,
+    {
+        MerkleTree {
+            root: rehash_node(&self.root, hasher, &mut leaf_input),
+        }
+    }
+}
+
+fn rehash_node<H, T, D, In, F>(
+    node: &Node<H, T>,
+    hasher: &D,
+    leaf_input: &mut F,
+) -> Node<D::HashOutput, T>
+where
+    T: Clone,
+    D: Hasher<In>,
+    F: FnMut(&LeafNode<H, T>) -> In,
+{
+    match *node {
+        Node::Leaf(ref ln) => {
+            let input = leaf_input(ln);
+            Node::Leaf(LeafNode {
+                hash: hasher.hash_input(&input),
+                data: ln.data.clone(),
+            })
+        }
+        Node::Hash(ref hn) => {
+            let children: Box<[_]> = hn
+                .children()
+                .map(|child| rehash_node(child, hasher, leaf_input))
+                .collect();
+            let hash = hasher
+                .hash_children_at_height(Children(children.iter()), hn.height);
+            Node::Hash(HashNode {
+                hash,
+                children,
+                leaf_count: hn.leaf_count,
+                height: hn.height,
+            })
+        }
+    }
+}
+
+impl<H: AsRef<[u8]>, T> MerkleTree<H, T> {
+    /// Checks whether the hash value of the root node, as a byte slice,
+    /// is equal to the expected bytes.
+    ///
+    /// This is a convenience for verifying a tree against a root hash
+    /// obtained from an external source.
+    pub fn root_hash_eq(&self, expected: &[u8]) -> bool {
+        self.root.hash_bytes() == expected
+    }
+}
+
+impl<H, T: AsRef<[u8]>> MerkleTree<H, T> {
+    /// Returns the sum of the lengths of the leaf data values
+    /// as byte slices.
+    pub fn total_data_len(&self) -> usize {
+        self.leaves().map(|ln| ln.data().as_ref().len()).sum()
+    }
+}
+
+impl<H: PartialEq, T> MerkleTree<H, T> {
+    /// Returns the number of initial leaves that this tree has in common
+    /// with another tree.
+    ///
+    /// This method is intended for trees built in the left-filled layouts
+    /// produced by `Builder::complete_tree_from()` or
+    /// `Builder::full_tree_from()`, where one tree is expected to
+    /// have been grown from the other by appending leaves. Both trees
+    /// are descended from the root, skipping over subtrees with equal
+    /// hashes and stopping at the first divergence. If the trees differ
+    /// in height, the leftmost subtree of the higher tree is compared.
+    /// The trees are assumed to have been built with the same
+    /// hasher configuration.
+    pub fn common_leaf_prefix(&self, other: &Self) -> usize {
+        common_leaf_prefix(&self.root, &other.root)
+    }
+
+    /// Checks whether this tree has been grown from an older tree by
+    /// appending leaves, that is, whether the leaves of `older` are
+    /// the initial leaves of this tree.
+    ///
+    /// This is a cheaper alternative to a consistency proof when both
+    /// trees are available. The same layout and hasher configuration
+    /// assumptions as for `common_leaf_prefix()` apply.
+    pub fn is_extension_of(&self, older: &Self) -> bool {
+        let older_count = older.root.leaf_count();
+        older_count <= self.root.leaf_count()
+            && self.common_leaf_prefix(older) == older_count
+    }
+
+    /// Checks whether any node of the tree, leaf or internal,
+    /// has the given hash value.
+    ///
+    /// The tree is traversed in preorder until the first match is found.
+    /// For repeated lookups in the same tree, it is more efficient to
+    /// collect the hash values into a set.
+    pub fn contains_hash(&self, hash: &H) -> bool {
+        let mut stack = vec![&self.root];
+        while let Some(node) = stack.pop() {
+            if node.hash() == hash {
+                return true;
+            }
+            if let Node::Hash(ref hn) = *node {
+                stack.extend(hn.children().rev());
+            }
+        }
+        false
+    }
+
+    /// Compares this tree with another tree that may have a different
+    /// type of leaf data.
+    ///
+    /// The trees are considered equal if they have the same shape and
+    /// the hash values of all corresponding nodes are equal, which is
+    /// the case for trees built out of the same input with the same
+    /// hasher, regardless of the leaf data extractors used. Unlike the
+    /// `PartialEq` implementation, this method compares every node rather
+    /// than relying on the hash value of the root alone.
+    pub fn structurally_eq<U>(&self, other: &MerkleTree<H, U>) -> bool {
+        let mut stack = vec![(&self.root, &other.root)];
+        while let Some((a, b)) = stack.pop() {
+            match (a, b) {
+                (Node::Leaf(a), Node::Leaf(b)) => {
+                    if a.hash != b.hash {
+                        return false;
+                    }
+                }
+                (Node::Hash(a), Node::Hash(b)) => {
+                    if a.hash != b.hash || a.children.len() != b.children.len()
+                    {
+                        return false;
+                    }
+                    stack.extend(a.children.iter().zip(b.children.iter()));
+                }
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+fn common_leaf_prefix<H: PartialEq, T>(
+    a: &Node<H, T>,
+    b: &Node<H, T>,
+) -> usize {
+    if a == b {
+        return a.leaf_count();
+    }
+    let a_height = a.leftmost_height();
+    let b_height = b.leftmost_height();
+    match (a, b) {
+        (Node::Hash(hn), _) if a_height > b_height => {
+            common_leaf_prefix(hn.child_at(0), b)
+        }
+        (_, Node::Hash(hn)) if b_height > a_height => {
+            common_leaf_prefix(a, hn.child_at(0))
+        }
+        (Node::Hash(a), Node::Hash(b)) => {
+            let mut count = 0;
+            for (a, b) in a.children().zip(b.children()) {
+                if a == b {
+                    count += a.leaf_count();
+                } else {
+                    count += common_leaf_prefix(a, b);
+                    break;
+                }
+            }
+            count
+        }
+        _ => 0,
+    }
+}
+
+impl<H, T> AsRef<Node<H, T>> for MerkleTree<H, T> {
+    fn as_ref(&self) -> &Node<H, T> {
+        self.root()
+    }
+}
+
+impl<H, T> AsRef<Node<H, T>> for Node<H, T> {
+    fn as_ref(&self) -> &Node<H, T> {
+        self
+    }
+}
+
+impl<'a, H, T> TryFrom<&'a Node<H, T>> for &'a LeafNode<H, T> {
+    type Error = NodeTypeMismatch;
+
+    fn try_from(node: &'a Node<H, T>) -> Result<Self, NodeTypeMismatch> {
+        match *node {
+            Node::Leaf(ref ln) => Ok(ln),
+            Node::Hash(_) => Err(NodeTypeMismatch),
+        }
+    }
+}
+
+impl<'a, H, T> TryFrom<&'a Node<H, T>> for &'a HashNode<H, T> {
+    type Error = NodeTypeMismatch;
+
+    fn try_from(node: &'a Node<H, T>) -> Result<Self, NodeTypeMismatch> {
+        match *node {
+            Node::Hash(ref hn) => Ok(hn),
+            Node::Leaf(_) => Err(NodeTypeMismatch),
+        }
+    }
+}
+
+/// The error returned when a `Node` reference is converted to a reference
+/// of a node type different from the type of the node.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct NodeTypeMismatch;
+
+impl Display for NodeTypeMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        f.write_str("the node is not of the requested type")
+    }
+}
+
+impl Error for NodeTypeMismatch {
+    fn description(&self) -> &str {
+        "node type mismatch"
+    }
 }
 
 impl<H: Debug, T: Debug> Debug for Node<H, T> {
@@ -109,6 +607,50 @@ impl<H, T> Node<H, T> {
             Node::Hash(ref hn) => &hn.hash,
         }
     }
+
+    /// Compares the hash value of this node with that of another node,
+    /// which may have a different type of leaf data.
+    ///
+    /// Unlike the `PartialEq` implementation, this does not distinguish
+    /// leaf nodes from hash nodes.
+    pub fn hash_matches<U>(&self, other: &Node<H, U>) -> bool
+    where
+        H: PartialEq,
+    {
+        self.hash() == other.hash()
+    }
+
+    fn leaf_count(&self) -> usize {
+        match *self {
+            Node::Leaf(_) => 1,
+            Node::Hash(ref hn) => hn.leaf_count,
+        }
+    }
+
+    fn uniform_height(&self) -> Option<usize> {
+        match *self {
+            Node::Leaf(_) => Some(0),
+            Node::Hash(ref hn) => {
+                let mut children = hn.children();
+                let height = children.next().unwrap().uniform_height()?;
+                for child in children {
+                    if child.uniform_height()? != height {
+                        return None;
+                    }
+                }
+                Some(height + 1)
+            }
+        }
+    }
+
+    // The number of edges on the path down to a leaf through the leftmost
+    // child nodes, as recorded in internal nodes when they are created.
+    fn leftmost_height(&self) -> usize {
+        match *self {
+            Node::Leaf(_) => 0,
+            Node::Hash(ref hn) => hn.height,
+        }
+    }
 }
 
 impl<H: AsRef<[u8]>, T> Node<H, T> {
@@ -126,6 +668,15 @@ impl<H: AsRef<[u8]>, T> LeafNode<H, T> {
 }
 
 impl<H, T> LeafNode<H, T> {
+    /// Constructs a leaf node value from a hash value and a leaf data value.
+    ///
+    /// This is mostly useful for testing code that works with node values.
+    /// Nodes constructed this way cannot be made part of a `MerkleTree`,
+    /// so the hash integrity of trees is not affected.
+    pub fn new(hash: H, data: T) -> Self {
+        LeafNode { hash, data }
+    }
+
     /// Returns a reference to the hash value of the node.
     pub fn hash(&self) -> &H {
         &self.hash
@@ -135,6 +686,17 @@ impl<H, T> LeafNode<H, T> {
     pub fn data(&self) -> &T {
         &self.data
     }
+
+    /// Returns references to the hash value and the leaf data value
+    /// of the node.
+    pub fn parts(&self) -> (&H, &T) {
+        (&self.hash, &self.data)
+    }
+
+    /// Consumes the node, returning the hash value and the leaf data value.
+    pub fn into_parts(self) -> (H, T) {
+        (self.hash, self.data)
+    }
 }
 
 impl<H: AsRef<[u8]>, T> HashNode<H, T> {
@@ -145,6 +707,30 @@ impl<H: AsRef<[u8]>, T> HashNode<H, T> {
 }
 
 impl<H, T> HashNode<H, T> {
+    /// Constructs an internal node value from a hash value and
+    /// the child nodes.
+    ///
+    /// The hash value is not checked against the child nodes;
+    /// `verify_own_hash()` can be used for that. This is mostly useful
+    /// for testing code that works with node values.
+    /// Nodes constructed this way cannot be made part of a `MerkleTree`,
+    /// so the hash integrity of trees is not affected.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `children` is empty.
+    pub fn from_parts(hash: H, children: Vec<Node<H, T>>) -> Self {
+        assert!(!children.is_empty(), "a hash node must have children");
+        let leaf_count = children.iter().map(|node| node.leaf_count()).sum();
+        let height = children[0].leftmost_height() + 1;
+        HashNode {
+            hash,
+            children: children.into_boxed_slice(),
+            leaf_count,
+            height,
+        }
+    }
+
     /// Returns a reference to the hash value of the node.
     pub fn hash(&self) -> &H {
         &self.hash
@@ -159,6 +745,48 @@ impl<H, T> HashNode<H, T> {
     pub fn children<'a>(&'a self) -> Children<'a, H, T> {
         Children(self.children.iter())
     }
+
+    /// Returns the number of child nodes.
+    pub fn child_count(&self) -> usize {
+        self.children.len()
+    }
+
+    /// Returns the height of the node, as passed to
+    /// `NodeHasher::hash_children_at_height()` when its hash value
+    /// was calculated.
+    ///
+    /// The height is recorded when the node is created, so this method
+    /// takes constant time.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Borrows the two child nodes of a binary node.
+    ///
+    /// Returns `None` if the node does not have exactly two children,
+    /// such as the lone-child nodes produced by
+    /// `Builder::chain_lone_child()`.
+    #[allow(clippy::type_complexity)]
+    pub fn as_pair(&self) -> Option<(&Node<H, T>, &Node<H, T>)> {
+        match *self.children {
+            [ref left, ref right] => Some((left, right)),
+            _ => None,
+        }
+    }
+
+    /// Checks the hash value of this node against the hash recomputed
+    /// from the child nodes with the given hasher.
+    ///
+    /// Only the hash values of the immediate children are used in the
+    /// check; the subtrees under the children are not verified.
+    pub fn verify_own_hash<D>(&self, hasher: &D) -> bool
+    where
+        D: NodeHasher<HashOutput = H>,
+        H: PartialEq,
+    {
+        hasher.hash_children_at_height(self.children(), self.height)
+            == self.hash
+    }
 }
 
 // NOTE: The PartialEq, Eq, and Hash implementations assume that the hashing
@@ -302,6 +930,15 @@ impl_hash_for! {
 #[derive(Debug)]
 pub struct Children<'a, H: 'a, T: 'a>(slice::Iter<'a, Node<H, T>>);
 
+impl<'a, H, T> Children<'a, H, T> {
+    /// Returns the remaining child nodes as a slice.
+    ///
+    /// This works like `as_slice()` of `std::slice::Iter`.
+    pub fn as_slice(&self) -> &'a [Node<H, T>] {
+        self.0.as_slice()
+    }
+}
+
 impl<'a, H, T> Clone for Children<'a, H, T> {
     fn clone(&self) -> Self {
         Children(self.0.clone())
@@ -338,3 +975,508 @@ impl<'a, H, T> DoubleEndedIterator for Children<'a, H, T> {
         self.0.next_back()
     }
 }
+
+/// An iterator over borrowed leaf nodes of a tree, in left-to-right order.
+///
+/// Values of this type are returned by the `leaves()` method
+/// of `MerkleTree`.
+#[derive(Debug)]
+pub struct Leaves<'a, H: 'a, T: 'a> {
+    stack: Vec<Children<'a, H, T>>,
+}
+
+impl<'a, H, T> Clone for Leaves<'a, H, T> {
+    fn clone(&self) -> Self {
+        Leaves {
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+impl<'a, H, T> Iterator for Leaves<'a, H, T> {
+    type Item = &'a LeafNode<H, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = match self.stack.last_mut()?.next() {
+                Some(node) => node,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            match *node {
+                Node::Leaf(ref ln) => return Some(ln),
+                Node::Hash(ref hn) => self.stack.push(hn.children()),
+            }
+        }
+    }
+}
+
+/// An iterator over borrowed internal nodes of a tree, in preorder.
+///
+/// Values of this type are returned by the `hash_nodes()` method
+/// of `MerkleTree`.
+#[derive(Debug)]
+pub struct HashNodes<'a, H: 'a, T: 'a> {
+    stack: Vec<Children<'a, H, T>>,
+}
+
+impl<'a, H, T> Clone for HashNodes<'a, H, T> {
+    fn clone(&self) -> Self {
+        HashNodes {
+            stack: self.stack.clone(),
+        }
+    }
+}
+
+impl<'a, H, T> Iterator for HashNodes<'a, H, T> {
+    type Item = &'a HashNode<H, T>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let node = match self.stack.last_mut()?.next() {
+                Some(node) => node,
+                None => {
+                    self.stack.pop();
+                    continue;
+                }
+            };
+            if let Node::Hash(ref hn) = *node {
+                self.stack.push(hn.children());
+                return Some(hn);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testmocks::MockHasher;
+    use super::NodeTypeMismatch;
+    use super::{Builder, HashNode, LeafNode, MerkleTree, Node};
+    use leaf;
+
+    const TEST_DATA: &'static [u8] =
+        b"The quick brown fox jumps over the lazy dog";
+
+    #[test]
+    fn common_leaf_prefix_of_grown_trees() {
+        let builder = Builder::<MockHasher, _>::new();
+        let chunks: Vec<_> = TEST_DATA.chunks(3).collect();
+        for n in 1..chunks.len() + 1 {
+            let tree = builder.complete_tree_from(&chunks[..n]).unwrap();
+            for k in 1..n + 1 {
+                let older = builder.complete_tree_from(&chunks[..k]).unwrap();
+                assert_eq!(tree.common_leaf_prefix(&older), k);
+                assert_eq!(older.common_leaf_prefix(&tree), k);
+            }
+            let tree = builder.full_tree_from(&chunks[..n]).unwrap();
+            for k in 1..n + 1 {
+                let older = builder.full_tree_from(&chunks[..k]).unwrap();
+                assert_eq!(tree.common_leaf_prefix(&older), k);
+            }
+        }
+    }
+
+    #[test]
+    fn common_leaf_prefix_stops_at_divergence() {
+        let builder = Builder::<MockHasher, _>::new();
+        let chunks: Vec<_> = TEST_DATA.chunks(3).collect();
+        let mut altered = chunks.clone();
+        altered[5] = b"cat";
+        let tree = builder.complete_tree_from(&chunks).unwrap();
+        let other = builder.complete_tree_from(&altered[..9]).unwrap();
+        assert_eq!(tree.common_leaf_prefix(&other), 5);
+        altered[0] = b"A q";
+        let other = builder.complete_tree_from(&altered).unwrap();
+        assert_eq!(tree.common_leaf_prefix(&other), 0);
+    }
+
+    #[test]
+    fn is_extension_of() {
+        let builder = Builder::<MockHasher, _>::new();
+        let chunks: Vec<_> = TEST_DATA.chunks(3).collect();
+        let tree = builder.complete_tree_from(&chunks).unwrap();
+        for k in 1..chunks.len() + 1 {
+            let older = builder.complete_tree_from(&chunks[..k]).unwrap();
+            assert!(tree.is_extension_of(&older));
+            assert_eq!(older.is_extension_of(&tree), k == chunks.len());
+        }
+        let mut altered = chunks.clone();
+        altered[5] = b"cat";
+        let other = builder.complete_tree_from(&altered[..9]).unwrap();
+        assert!(!tree.is_extension_of(&other));
+    }
+
+    #[test]
+    fn verify_own_hash() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let hasher = MockHasher::default();
+        if let Node::Hash(ref hn) = *tree.root() {
+            assert_eq!(hn.child_count(), 2);
+            assert_eq!(hn.height(), 3);
+            let mut children = hn.children();
+            children.next();
+            assert_eq!(children.as_slice().len(), 1);
+            assert_eq!(children.as_slice()[0], *hn.child_at(1));
+            assert!(hn.verify_own_hash(&hasher));
+            let leaf = builder.make_leaf(TEST_DATA);
+            let forged = HashNode {
+                hash: b"forged".to_vec(),
+                children: Box::new([leaf.root]),
+                leaf_count: 1,
+                height: 1,
+            };
+            assert!(!forged.verify_own_hash(&hasher));
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn uniform_depth() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(7)).unwrap();
+        assert!(tree.is_uniform_depth());
+        let tree = builder.full_tree_from(TEST_DATA.chunks(7)).unwrap();
+        assert!(!tree.is_uniform_depth());
+        let leaf = builder.make_leaf(TEST_DATA);
+        assert!(leaf.is_uniform_depth());
+        let left =
+            builder.join(builder.make_leaf(b"a"), builder.make_leaf(b"b"));
+        let tree = builder.join(left, builder.make_leaf(b"c"));
+        assert!(!tree.is_uniform_depth());
+    }
+
+    #[test]
+    fn uniform_arity() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert_eq!(tree.is_uniform_arity(), Some(2));
+        let tree = builder.full_tree_from(TEST_DATA.chunks(7)).unwrap();
+        assert_eq!(tree.is_uniform_arity(), Some(2));
+        let leaf = builder.make_leaf(TEST_DATA);
+        assert_eq!(leaf.is_uniform_arity(), None);
+
+        let node = |range: ::std::ops::Range<usize>| {
+            let leaves =
+                TEST_DATA[range].chunks(1).map(|c| builder.make_leaf(c));
+            builder.collect_children_from(leaves).unwrap()
+        };
+        let tree = builder
+            .collect_children_from(vec![node(0..3), node(3..6), node(6..7)])
+            .unwrap();
+        assert_eq!(tree.is_uniform_arity(), Some(3));
+        let tree = builder
+            .collect_children_from(vec![node(0..3), node(3..5), node(5..8)])
+            .unwrap();
+        assert_eq!(tree.is_uniform_arity(), None);
+        let tree = builder
+            .collect_children_from(vec![node(0..1), node(1..3)])
+            .unwrap();
+        assert_eq!(tree.is_uniform_arity(), None);
+    }
+
+    #[test]
+    fn leaf_at_agrees_with_leaves() {
+        let builder = Builder::<MockHasher, _>::new();
+        let chunks: Vec<_> = TEST_DATA.chunks(3).collect();
+        for n in 1..chunks.len() + 1 {
+            let trees = [
+                builder.complete_tree_from(&chunks[..n]).unwrap(),
+                builder.full_tree_from(&chunks[..n]).unwrap(),
+            ];
+            for tree in trees.iter() {
+                assert_eq!(tree.leaves().count(), n);
+                for (i, leaf) in tree.leaves().enumerate() {
+                    assert_eq!(leaf.hash_bytes(), chunks[i]);
+                    assert_eq!(tree.leaf_at(i).unwrap(), leaf);
+                }
+                assert!(tree.leaf_at(n).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn siblings_at_levels() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let siblings = tree.siblings_at(2, 0).unwrap();
+        assert_eq!(siblings, vec![tree.leaf_at(3).unwrap().hash()]);
+        let siblings = tree.siblings_at(4, 0).unwrap();
+        assert!(siblings.is_empty());
+        let siblings = tree.siblings_at(4, 1).unwrap();
+        assert!(siblings.is_empty());
+        let siblings = tree.siblings_at(4, 2).unwrap();
+        if let Node::Hash(ref hn) = *tree.root() {
+            assert_eq!(siblings, vec![hn.child_at(0).hash()]);
+        } else {
+            unreachable!()
+        }
+        assert!(tree.siblings_at(4, 3).is_none());
+        assert!(tree.siblings_at(5, 0).is_none());
+    }
+
+    #[test]
+    fn collect_leaf_data_and_hashes() {
+        let builder = Builder::from_hasher_leaf_data(
+            MockHasher::default(),
+            leaf::owned(),
+        );
+        let chunks: Vec<_> = TEST_DATA.chunks(10).collect();
+        let tree = builder.full_tree_from(&chunks).unwrap();
+        let data = tree.collect_leaf_data();
+        assert_eq!(data.len(), chunks.len());
+        for (d, chunk) in data.iter().zip(&chunks) {
+            assert_eq!(&d[..], *chunk);
+        }
+        let hashes = tree.collect_leaf_hashes();
+        for (h, chunk) in hashes.iter().zip(&chunks) {
+            assert_eq!(&h[..], *chunk);
+        }
+    }
+
+    #[test]
+    fn contains_hash() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert!(tree.contains_hash(tree.root().hash()));
+        for leaf in tree.leaves() {
+            assert!(tree.contains_hash(leaf.hash()));
+        }
+        for hn in tree.hash_nodes() {
+            assert!(tree.contains_hash(hn.hash()));
+        }
+        assert!(!tree.contains_hash(&b"The quick".to_vec()));
+    }
+
+    #[test]
+    fn total_data_len() {
+        let builder = Builder::from_hasher_leaf_data(
+            MockHasher::default(),
+            leaf::owned(),
+        );
+        let tree = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert_eq!(tree.total_data_len(), TEST_DATA.len());
+        let leaf = builder.make_leaf(TEST_DATA);
+        assert_eq!(leaf.total_data_len(), TEST_DATA.len());
+    }
+
+    #[test]
+    fn root_hash_eq() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(15)).unwrap();
+        let expected: &[u8] = b"#(>The quick brown> fox jumps over)\
+                                #(> the lazy dog)";
+        assert!(tree.root_hash_eq(expected));
+        assert!(!tree.root_hash_eq(TEST_DATA));
+    }
+
+    #[test]
+    fn into_root() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let expected = tree.root().hash().clone();
+        assert_eq!(*tree.into_root().hash(), expected);
+    }
+
+    #[test]
+    fn trees_are_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<MerkleTree<[u8; 32], ()>>();
+        assert_send_sync::<Node<[u8; 32], Vec<u8>>>();
+    }
+
+    #[test]
+    fn rehash_with() {
+        let builder = Builder::from_hasher_leaf_data(
+            MockHasher::default(),
+            leaf::owned(),
+        );
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(15)).unwrap();
+        let hasher = MockHasher::default();
+        let rehashed =
+            tree.rehash_with(&hasher, |ln| ln.data().to_ascii_uppercase());
+        let expected: &[u8] = b"#(>THE QUICK BROWN> FOX JUMPS OVER)\
+                                #(> THE LAZY DOG)";
+        assert_eq!(rehashed.root().hash_bytes(), expected);
+        let data: Vec<_> = rehashed.leaves().map(|ln| *ln.data()).collect();
+        assert_eq!(data, TEST_DATA.chunks(15).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn fold() {
+        let builder = Builder::from_hasher_leaf_data(
+            MockHasher::default(),
+            leaf::owned(),
+        );
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let sum = tree.fold(
+            |ln| ln.data().len(),
+            |_, child_sums| child_sums.into_iter().sum(),
+        );
+        assert_eq!(sum, TEST_DATA.len());
+        let max_depth: usize =
+            tree.fold(|_| 0, |_, depths| depths.into_iter().max().unwrap() + 1);
+        assert_eq!(max_depth, 3);
+        let leaf = builder.make_leaf(TEST_DATA);
+        assert_eq!(leaf.fold(|ln| ln.data().len(), |_, _| 0), TEST_DATA.len());
+    }
+
+    #[test]
+    fn structurally_eq_ignores_leaf_data() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let data_builder =
+            Builder::from_hasher_leaf_data(MockHasher, leaf::owned());
+        let data_tree = data_builder
+            .complete_tree_from(TEST_DATA.chunks(10))
+            .unwrap();
+        assert!(tree.structurally_eq(&data_tree));
+        assert!(data_tree.structurally_eq(&tree));
+        let other = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert!(!tree.structurally_eq(&other));
+        let other = builder.complete_tree_from(TEST_DATA.chunks(9)).unwrap();
+        assert!(!tree.structurally_eq(&other));
+    }
+
+    #[test]
+    fn leaf_node_parts() {
+        let builder = Builder::from_hasher_leaf_data(MockHasher, leaf::owned());
+        let tree = builder.make_leaf(&TEST_DATA[..3]);
+        {
+            let ln = tree.leaves().next().unwrap();
+            let (hash, data) = ln.parts();
+            assert_eq!(hash, ln.hash());
+            assert_eq!(data, ln.data());
+        }
+        if let Node::Leaf(ln) = tree.into_root() {
+            let (hash, data) = ln.into_parts();
+            assert_eq!(hash, b"The");
+            assert_eq!(data, b"The");
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn hash_nodes_in_preorder() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.make_leaf(&TEST_DATA[..4]);
+        assert_eq!(tree.hash_nodes().count(), 0);
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(15)).unwrap();
+        let hashes: Vec<&[u8]> =
+            tree.hash_nodes().map(|hn| hn.hash_bytes()).collect();
+        assert_eq!(
+            hashes,
+            vec![
+                &b"#(>The quick brown> fox jumps over)#(> the lazy dog)"[..],
+                &b">The quick brown> fox jumps over"[..],
+                &b"> the lazy dog"[..],
+            ]
+        );
+    }
+
+    #[test]
+    fn indexed_leaves_agree_with_leaf_at() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.full_tree_from(TEST_DATA.chunks(7)).unwrap();
+        let mut count = 0;
+        for (i, leaf) in tree.indexed_leaves() {
+            assert_eq!(leaf, tree.leaf_at(i).unwrap());
+            count += 1;
+        }
+        assert_eq!(count, 7);
+    }
+
+    #[test]
+    fn tree_as_ref_node() {
+        fn leaf_count<N: AsRef<Node<Vec<u8>, ()>>>(node: N) -> usize {
+            node.as_ref().leaf_count()
+        }
+
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        assert_eq!(leaf_count(&tree), 5);
+        assert_eq!(leaf_count(tree.root()), 5);
+    }
+
+    #[test]
+    fn to_levels() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.full_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let levels = tree.to_levels();
+        assert_eq!(levels.len(), 4);
+        let leaves: Vec<_> =
+            tree.leaves().take(4).map(|ln| ln.hash().clone()).collect();
+        assert_eq!(levels[0], leaves);
+        assert_eq!(levels[2].len(), 2);
+        assert_eq!(levels[2][1], b"dog");
+        assert_eq!(levels[3], [tree.root().hash().clone()]);
+    }
+
+    #[test]
+    fn node_try_into() {
+        use std::convert::TryInto;
+
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+        let hn: &HashNode<_, _> = tree.root().try_into().unwrap();
+        assert_eq!(hn.hash(), tree.root().hash());
+        let res: Result<&LeafNode<_, _>, _> = tree.root().try_into();
+        assert_eq!(res.unwrap_err(), NodeTypeMismatch);
+        let leaf = builder.make_leaf(TEST_DATA);
+        let ln: &LeafNode<_, _> = leaf.root().try_into().unwrap();
+        assert_eq!(ln.hash_bytes(), TEST_DATA);
+    }
+
+    #[test]
+    fn as_pair() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(15)).unwrap();
+        if let Node::Hash(ref hn) = *tree.root() {
+            let (left, right) = hn.as_pair().unwrap();
+            assert_eq!(left, hn.child_at(0));
+            assert_eq!(right, hn.child_at(1));
+            if let Node::Hash(ref hn) = *right {
+                assert!(hn.as_pair().is_none());
+            } else {
+                unreachable!()
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    #[test]
+    fn nodes_from_parts() {
+        let builder = Builder::<MockHasher, _>::new();
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(25)).unwrap();
+        let children = TEST_DATA
+            .chunks(25)
+            .map(|chunk| Node::Leaf(LeafNode::new(chunk.to_vec(), ())))
+            .collect();
+        let hn = HashNode::from_parts(tree.root().hash().clone(), children);
+        assert!(hn.verify_own_hash(&MockHasher::default()));
+        assert_eq!(*tree.root(), Node::Hash(hn));
+    }
+
+    #[test]
+    fn hash_matches_across_leaf_data_types() {
+        let builder = Builder::<MockHasher, _>::new();
+        let owned_builder = Builder::from_hasher_leaf_data(
+            MockHasher::default(),
+            leaf::owned(),
+        );
+        let tree = builder.complete_tree_from(TEST_DATA.chunks(7)).unwrap();
+        let owned_tree = owned_builder
+            .complete_tree_from(TEST_DATA.chunks(7))
+            .unwrap();
+        assert!(tree.root().hash_matches(owned_tree.root()));
+        let other = owned_builder
+            .complete_tree_from(TEST_DATA.chunks(8))
+            .unwrap();
+        assert!(!tree.root().hash_matches(other.root()));
+    }
+}
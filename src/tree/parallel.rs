@@ -57,10 +57,18 @@ pub extern crate rayon;
 use self::rayon::prelude::*;
 
 use super::plumbing::FromNodes;
-use hash::Hasher;
+use hash::{Hasher, NodeHasher};
 use leaf;
 use tree;
-use tree::{BuildResult, EmptyTree, MerkleTree};
+use tree::{BuildError, BuildResult, MerkleTree, Node};
+
+use std::collections::LinkedList;
+use std::ops::Range;
+
+/// The result type of `Builder::complete_tree_from_indexed()`, carrying
+/// the tree and the mapping of its leaf positions to input indices.
+pub type IndexedBuildResult<H, T> =
+    Result<(MerkleTree<H, T>, Vec<usize>), BuildError>;
 
 /// A parallel Merkle tree builder utilizing a work-stealing thread pool.
 ///
@@ -105,6 +113,18 @@ where
         Builder { inner }
     }
 
+    /// Borrows the sequential `tree::Builder` with the same hasher
+    /// and leaf data extractor as this `Builder`.
+    pub fn as_sequential(&self) -> &tree::Builder<D, L> {
+        &self.inner
+    }
+
+    /// Converts this `Builder` into a sequential `tree::Builder`
+    /// with the same hasher and leaf data extractor.
+    pub fn into_sequential(self) -> tree::Builder<D, L> {
+        self.inner
+    }
+
     /// Transforms input data into a tree consisting of a single leaf node.
     ///
     /// This method is not parallelized internally, but it is provided to
@@ -186,7 +206,11 @@ where
     ///
     /// # Errors
     ///
-    /// Returns the `EmptyTree` error when the input is empty.
+    /// Returns `BuildError::Empty` when the input is empty.
+    /// Returns `BuildError::LengthMismatch` if the parallel iterator
+    /// produces a number of items different from its reported length.
+    /// Returns `BuildError::TooManyLeaves` when the length of the input
+    /// exceeds the largest power of two representable by `usize`.
     ///
     pub fn complete_tree_from<I>(
         &self,
@@ -199,6 +223,70 @@ where
         self.complete_tree_from_iter(iterable.into_par_iter())
     }
 
+    /// Constructs a tree like `complete_tree_from()`, also returning
+    /// the mapping of leaf positions to indices in the input sequence.
+    ///
+    /// The element at position *i* in the returned vector is the index
+    /// of the input item that was used to make the *i*-th leaf of
+    /// the tree, counting from the left. The leaves of the trees
+    /// constructed by this builder are guaranteed to be in the same order
+    /// as the items of the input sequence, so the mapping is the identity;
+    /// it is returned to make the ordering contract explicit to callers
+    /// relying on it.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `complete_tree_from()`.
+    ///
+    pub fn complete_tree_from_indexed<I>(
+        &self,
+        iterable: I,
+    ) -> IndexedBuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoParallelIterator<Item = L::Input>,
+        I::Iter: IndexedParallelIterator,
+    {
+        let iter = iterable.into_par_iter();
+        let len = iter.len();
+        let tree = self.complete_tree_from_iter(iter)?;
+        // The build fails unless the iterator produces the reported
+        // number of items, so the length is that of the tree.
+        let order = (0..len).collect();
+        Ok((tree, order))
+    }
+
+    /// Constructs a tree like `complete_tree_from()`, calling the given
+    /// closure as each subtree under construction is completed.
+    ///
+    /// The closure receives the root hash of every internal node made
+    /// by the build, and the range of leaf indices, counted from the left
+    /// of the whole tree, that the subtree spans. The calls are made
+    /// bottom-up along each branch, but may come concurrently from
+    /// different threads of the Rayon pool and in no particular order
+    /// across branches. This can be used to checkpoint subtree roots of
+    /// a long parallel build as soon as they become available.
+    /// The constructed tree is the same as with `complete_tree_from()`.
+    ///
+    /// # Errors
+    ///
+    /// Returns the same errors as `complete_tree_from()`.
+    ///
+    pub fn complete_tree_with_callback<I, F>(
+        &self,
+        iterable: I,
+        on_subtree_complete: F,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IntoParallelIterator<Item = L::Input>,
+        I::Iter: IndexedParallelIterator,
+        F: Fn(&D::HashOutput, Range<usize>) + Sync,
+    {
+        self.complete_tree_from_iter_with(
+            iterable.into_par_iter(),
+            &on_subtree_complete,
+        )
+    }
+
     fn complete_tree_from_iter<I>(
         &self,
         iter: I,
@@ -206,47 +294,118 @@ where
     where
         I: IndexedParallelIterator<Item = L::Input>,
     {
-        if iter.len() == 0 {
-            return Err(EmptyTree);
+        self.complete_tree_from_iter_with(iter, &|_, _| {})
+    }
+
+    fn complete_tree_from_iter_with<I, F>(
+        &self,
+        iter: I,
+        on_subtree_complete: &F,
+    ) -> BuildResult<D::HashOutput, L::LeafData>
+    where
+        I: IndexedParallelIterator<Item = L::Input>,
+        F: Fn(&D::HashOutput, Range<usize>) + Sync,
+    {
+        let len = iter.len();
+        if len == 0 {
+            return Err(BuildError::Empty);
         }
-        let leaves: Vec<_> = iter
+        let leaves = self.make_leaves(iter);
+        if leaves.len() != len {
+            return Err(BuildError::LengthMismatch);
+        }
+        let perfect_len = leaves
+            .len()
+            .checked_next_power_of_two()
+            .ok_or(BuildError::TooManyLeaves)?;
+        Ok(self.reduce_complete(leaves, perfect_len, 0, on_subtree_complete))
+    }
+
+    fn make_leaves<I>(
+        &self,
+        iter: I,
+    ) -> Vec<MerkleTree<D::HashOutput, L::LeafData>>
+    where
+        I: IndexedParallelIterator<Item = L::Input>,
+    {
+        let len = iter.len();
+        // Collecting an indexed parallel iterator directly into a Vec
+        // panics if the iterator does not produce the reported number
+        // of items. Collect unindexed chunks instead, so that the length
+        // can be checked afterwards.
+        let chunks: LinkedList<Vec<_>> = iter
             .map_with(self.clone(), |master, input| master.make_leaf(input))
+            .fold(Vec::new, |mut chunk, leaf| {
+                chunk.push(leaf);
+                chunk
+            })
             .collect();
-        assert!(
-            leaves.len() != 0,
-            "the parallel iterator that reported nonzero length \
-             has come up empty"
+        let mut leaves = Vec::with_capacity(len);
+        for mut chunk in chunks {
+            leaves.append(&mut chunk);
+        }
+        debug_assert!(
+            leaves.len() == len,
+            "the parallel iterator reported length {}, \
+             but produced {} items",
+            len,
+            leaves.len()
         );
-        let perfect_len = leaves.len().checked_next_power_of_two().unwrap();
-        Ok(self.reduce_complete(leaves, perfect_len))
+        leaves
     }
 
-    fn reduce_complete(
+    fn reduce_complete<F>(
         &self,
         mut level_nodes: Vec<MerkleTree<D::HashOutput, L::LeafData>>,
         perfect_len: usize,
-    ) -> MerkleTree<D::HashOutput, L::LeafData> {
+        offset: usize,
+        on_subtree_complete: &F,
+    ) -> MerkleTree<D::HashOutput, L::LeafData>
+    where
+        F: Fn(&D::HashOutput, Range<usize>) + Sync,
+    {
         let len = level_nodes.len();
         debug_assert!(len != 0);
         let left_len = perfect_len / 2;
-        if len <= left_len {
+        let tree = if len <= left_len {
             // We're going to have no right subtree on this node.
             // And it's still an internal node because this is never true
             // when perfect_len == 1.
-            let subtree = self.reduce_complete(level_nodes, left_len);
+            let subtree = self.reduce_complete(
+                level_nodes,
+                left_len,
+                offset,
+                on_subtree_complete,
+            );
             self.chain_lone_child(subtree)
         } else if len == 1 {
-            level_nodes.pop().unwrap()
+            return level_nodes.pop().unwrap();
         } else {
             let right = level_nodes.split_off(left_len);
             let left = level_nodes;
             let left_builder = self.clone();
             let right_builder = self.clone();
             self.join(
-                move || left_builder.reduce_complete(left, left_len),
-                move || right_builder.reduce_complete(right, left_len),
+                move || {
+                    left_builder.reduce_complete(
+                        left,
+                        left_len,
+                        offset,
+                        on_subtree_complete,
+                    )
+                },
+                move || {
+                    right_builder.reduce_complete(
+                        right,
+                        left_len,
+                        offset + left_len,
+                        on_subtree_complete,
+                    )
+                },
             )
-        }
+        };
+        on_subtree_complete(tree.root().hash(), offset..offset + len);
+        tree
     }
 
     /// Constructs a [full][nist-full] binary Merkle tree from a parallel
@@ -282,7 +441,9 @@ where
     ///
     /// # Errors
     ///
-    /// Returns the `EmptyTree` error when the input is empty.
+    /// Returns `BuildError::Empty` when the input is empty.
+    /// Returns `BuildError::LengthMismatch` if the parallel iterator
+    /// produces a number of items different from its reported length.
     ///
     pub fn full_tree_from<I>(
         &self,
@@ -302,17 +463,14 @@ where
     where
         I: IndexedParallelIterator<Item = L::Input>,
     {
-        if iter.len() == 0 {
-            return Err(EmptyTree);
+        let len = iter.len();
+        if len == 0 {
+            return Err(BuildError::Empty);
+        }
+        let leaves = self.make_leaves(iter);
+        if leaves.len() != len {
+            return Err(BuildError::LengthMismatch);
         }
-        let leaves: Vec<_> = iter
-            .map_with(self.clone(), |master, input| master.make_leaf(input))
-            .collect();
-        assert!(
-            leaves.len() != 0,
-            "the parallel iterator that reported nonzero length \
-             has come up empty"
-        );
         Ok(self.reduce_full(leaves))
     }
 
@@ -369,12 +527,16 @@ where
     /// iterative computation as child nodes for the root of the
     /// returned tree.
     ///
+    /// The child nodes are placed in the order of the items of the
+    /// parallel iterator, such as the order of indices for an indexed
+    /// iterator, regardless of the order in which the parallel
+    /// computations of the subtrees complete.
     /// The `hash_children()` method of the hash extractor is used to obtain
     /// the root hash.
     ///
     /// # Errors
     ///
-    /// Returns the `EmptyTree` error when the iteration turns out empty.
+    /// Returns `BuildError::Empty` when the iteration turns out empty.
     ///
     pub fn collect_children_from<I>(
         &self,
@@ -398,17 +560,90 @@ where
     }
 }
 
+/// Returns a parallel iterator over the leaf nodes of a tree.
+///
+/// The leaves are indexed in the same left-to-right order as visited
+/// by `MerkleTree::leaves()`. References to the leaf nodes are collected
+/// sequentially before the parallel iteration starts, which takes time
+/// and memory proportional to the number of leaves.
+pub fn par_leaves<H, T>(
+    tree: &MerkleTree<H, T>,
+) -> impl IndexedParallelIterator<Item = &tree::LeafNode<H, T>>
+where
+    H: Sync,
+    T: Sync,
+{
+    let leaves: Vec<_> = tree.leaves().collect();
+    leaves.into_par_iter()
+}
+
+/// Verifies the integrity of a Merkle tree against the input values
+/// from which its leaves are expected to have been made.
+///
+/// The hash values of the leaf nodes are recalculated from the input
+/// values, and the hash value of every internal node is recalculated
+/// from its children, with the work distributed across the Rayon
+/// thread pool. Returns `true` if all the recalculated hash values
+/// are equal to those stored in the tree, and the number of input values
+/// is equal to the number of the tree's leaves.
+pub fn verify<D, T, I>(
+    tree: &MerkleTree<D::HashOutput, T>,
+    hasher: &D,
+    inputs: I,
+) -> bool
+where
+    D: Hasher<I::Item> + Sync,
+    D::HashOutput: PartialEq + Sync,
+    T: Sync,
+    I: IndexedParallelIterator,
+{
+    if inputs.len() != tree.root.leaf_count() {
+        return false;
+    }
+    let leaves: Vec<_> = tree.leaves().collect();
+    let (leaves_ok, nodes_ok) = rayon::join(
+        || {
+            inputs
+                .zip(leaves)
+                .all(|(input, leaf)| hasher.hash_input(&input) == leaf.hash)
+        },
+        || verify_node(&tree.root, hasher),
+    );
+    leaves_ok && nodes_ok
+}
+
+fn verify_node<D, T>(node: &Node<D::HashOutput, T>, hasher: &D) -> bool
+where
+    D: NodeHasher + Sync,
+    D::HashOutput: PartialEq + Sync,
+    T: Sync,
+{
+    match *node {
+        Node::Leaf(_) => true,
+        Node::Hash(ref hn) => {
+            hn.verify_own_hash(hasher)
+                && hn
+                    .children
+                    .par_iter()
+                    .all(|child| verify_node(child, hasher))
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::Builder;
+    use super::{par_leaves, verify, Builder};
 
     use super::rayon::iter;
     use super::rayon::prelude::*;
 
     use super::super::testmocks::MockHasher;
     use leaf;
+    use tree;
     use tree::Node;
 
+    use std::sync::Mutex;
+
     const TEST_DATA: &'static [u8] =
         b"The quick brown fox jumps over the lazy dog";
 
@@ -446,6 +681,47 @@ mod tests {
         }
     }
 
+    #[test]
+    fn complete_tree_with_callback() {
+        let builder = Builder::<MockHasher, _>::new();
+        let data: Vec<_> = TEST_DATA.chunks(15).collect();
+        let completed = Mutex::new(Vec::new());
+        let tree = builder
+            .complete_tree_with_callback(data.clone(), |hash, span| {
+                completed.lock().unwrap().push((hash.clone(), span));
+            })
+            .unwrap();
+        assert_eq!(tree, builder.complete_tree_from(data).unwrap());
+        let mut completed = completed.into_inner().unwrap();
+        completed.sort_by_key(|&(_, ref span)| (span.start, span.end));
+        let expected: Vec<(&[u8], _)> = vec![
+            (b">The quick brown> fox jumps over", 0..2),
+            (
+                b"#(>The quick brown> fox jumps over)#(> the lazy dog)",
+                0..3,
+            ),
+            (b"> the lazy dog", 2..3),
+        ];
+        assert_eq!(completed.len(), expected.len());
+        for (&(ref hash, ref span), &(exp_hash, ref exp_span)) in
+            completed.iter().zip(expected.iter())
+        {
+            assert_eq!(&hash[..], exp_hash);
+            assert_eq!(span, exp_span);
+        }
+    }
+
+    #[test]
+    fn par_leaves_agree_with_leaves() {
+        let builder = Builder::<MockHasher, _>::new();
+        let data: Vec<_> = TEST_DATA.chunks(3).collect();
+        let tree = builder.complete_tree_from(data).unwrap();
+        let leaves: Vec<_> = par_leaves(&tree).collect();
+        assert_eq!(leaves, tree.leaves().collect::<Vec<_>>());
+        let total: usize = par_leaves(&tree).map(|ln| ln.hash().len()).sum();
+        assert_eq!(total, TEST_DATA.len());
+    }
+
     #[test]
     fn complete_tree_is_subgraph_of_its_math_definition() {
         let builder = Builder::<MockHasher, _>::new();
@@ -479,6 +755,18 @@ mod tests {
         }
     }
 
+    #[test]
+    fn complete_tree_indexed() {
+        let builder = Builder::<MockHasher, _>::new();
+        let data: Vec<_> = TEST_DATA.chunks(10).collect();
+        let (tree, order) =
+            builder.complete_tree_from_indexed(data.clone()).unwrap();
+        assert_eq!(order.len(), data.len());
+        for (leaf, &index) in tree.leaves().zip(order.iter()) {
+            assert_eq!(leaf.hash_bytes(), data[index]);
+        }
+    }
+
     #[test]
     fn cant_make_full_from_empty() {
         use super::rayon::iter::empty;
@@ -528,4 +816,132 @@ mod tests {
             unreachable!()
         }
     }
+
+    #[test]
+    fn collect_children_in_index_order() {
+        use std::thread;
+        use std::time::Duration;
+
+        let builder = Builder::<MockHasher, leaf::NoData<&'static str>>::new();
+        let len = TEST_STRS.len();
+        let iter = TEST_STRS.par_iter().enumerate().map_with(
+            builder.clone(),
+            move |builder, (i, input)| {
+                // Make the computations complete in reverse order.
+                thread::sleep(Duration::from_millis(10 * (len - i) as u64));
+                builder.make_leaf(*input)
+            },
+        );
+        let tree = builder.collect_children_from(iter).unwrap();
+        assert_eq!(
+            tree.root().hash_bytes(),
+            b">Panda eats,>shoots,>and leaves."
+        );
+    }
+
+    #[test]
+    fn same_trees_as_sequential_builder() {
+        let seq_builder = tree::Builder::<MockHasher, _>::new();
+        let par_builder = Builder::<MockHasher, _>::new();
+        let input: Vec<[u8; 1]> = (0..64).map(|i| [i as u8]).collect();
+        for n in 1..input.len() + 1 {
+            let data = &input[..n];
+            let expected = seq_builder.complete_tree_from(data).unwrap();
+            let tree = par_builder.complete_tree_from(data.to_vec()).unwrap();
+            assert_eq!(tree.root().hash_bytes(), expected.root().hash_bytes());
+            let expected = seq_builder.full_tree_from(data).unwrap();
+            let tree = par_builder.full_tree_from(data.to_vec()).unwrap();
+            assert_eq!(tree.root().hash_bytes(), expected.root().hash_bytes());
+        }
+    }
+
+    #[test]
+    fn fall_back_to_sequential() {
+        let builder = Builder::<MockHasher, _>::new();
+        let data: Vec<_> = TEST_DATA.chunks(7).collect();
+        let expected = builder.complete_tree_from(data.clone()).unwrap();
+        let tree = builder
+            .as_sequential()
+            .complete_tree_from(data.clone())
+            .unwrap();
+        assert_eq!(tree, expected);
+        let seq_builder = builder.into_sequential();
+        let tree = seq_builder.complete_tree_from(data).unwrap();
+        assert_eq!(tree, expected);
+    }
+
+    #[test]
+    fn verify_against_inputs() {
+        let builder = Builder::<MockHasher, _>::new();
+        let hasher = MockHasher::default();
+        let data: Vec<_> = TEST_DATA.chunks(7).collect();
+        let tree = builder.complete_tree_from(data.clone()).unwrap();
+        assert!(verify(&tree, &hasher, data.par_iter()));
+        let tree = builder.full_tree_from(data.clone()).unwrap();
+        assert!(verify(&tree, &hasher, data.par_iter()));
+        assert!(!verify(&tree, &hasher, data[1..].par_iter()));
+        let mut altered = data.clone();
+        altered[3] = b"UMPS OV";
+        assert!(!verify(&tree, &hasher, altered.par_iter()));
+    }
+
+    // In debug builds, a mismatch trips the debug assertion in
+    // make_leaves() before the error can be returned.
+    #[cfg(not(debug_assertions))]
+    #[test]
+    fn iterator_length_mismatch() {
+        use super::rayon::iter::plumbing::{
+            Consumer, ProducerCallback, UnindexedConsumer,
+        };
+        use tree::BuildError;
+
+        struct Liar<I> {
+            inner: I,
+            reported: usize,
+        }
+
+        impl<I: IndexedParallelIterator> ParallelIterator for Liar<I> {
+            type Item = I::Item;
+            fn drive_unindexed<C>(self, consumer: C) -> C::Result
+            where
+                C: UnindexedConsumer<I::Item>,
+            {
+                self.inner.drive_unindexed(consumer)
+            }
+            fn opt_len(&self) -> Option<usize> {
+                Some(self.reported)
+            }
+        }
+
+        impl<I: IndexedParallelIterator> IndexedParallelIterator for Liar<I> {
+            fn len(&self) -> usize {
+                self.reported
+            }
+            fn drive<C: Consumer<I::Item>>(self, consumer: C) -> C::Result {
+                self.inner.drive(consumer)
+            }
+            fn with_producer<CB>(self, callback: CB) -> CB::Output
+            where
+                CB: ProducerCallback<I::Item>,
+            {
+                self.inner.with_producer(callback)
+            }
+        }
+
+        let builder = Builder::<MockHasher, _>::new();
+        for &reported in &[3, 6] {
+            let liar = || Liar {
+                inner: TEST_DATA.par_chunks(10),
+                reported,
+            };
+            assert_eq!(
+                builder.complete_tree_from(liar()).unwrap_err(),
+                BuildError::LengthMismatch
+            );
+            assert_eq!(
+                builder.full_tree_from(liar()).unwrap_err(),
+                BuildError::LengthMismatch
+            );
+        }
+    }
 }
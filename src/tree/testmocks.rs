@@ -6,7 +6,7 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use hash::{Hasher, NodeHasher};
+use hash::{ContextHasher, ContextNodeHasher, Hasher, NodeHasher, PairHasher};
 use tree::{Children, Node};
 
 #[derive(Clone, Debug, Default)]
@@ -42,3 +42,36 @@ impl NodeHasher for MockHasher {
         dump
     }
 }
+
+impl PairHasher for MockHasher {
+    fn hash_pair(
+        &self,
+        left: &Vec<u8>,
+        left_height: usize,
+        right: &Vec<u8>,
+        right_height: usize,
+    ) -> Vec<u8> {
+        let mut dump = Vec::new();
+        for &(hash, height) in &[(left, left_height), (right, right_height)] {
+            if height == 0 {
+                dump.push(b'>');
+                dump.extend(hash);
+            } else {
+                dump.extend(b"#(");
+                dump.extend(hash);
+                dump.extend(b")");
+            }
+        }
+        dump
+    }
+}
+
+impl ContextNodeHasher<&[u8]> for MockHasher {}
+
+impl<'c, In: AsRef<[u8]>> ContextHasher<&'c [u8], In> for MockHasher {
+    fn hash_input_with_context(&self, salt: &&'c [u8], input: &In) -> Vec<u8> {
+        let mut hash = salt.to_vec();
+        hash.extend(input.as_ref());
+        hash
+    }
+}
@@ -0,0 +1,93 @@
+#![cfg(test)]
+
+// Trees over a hash type that is not a byte array, such as a field
+// element produced by an algebraic hash function.
+
+extern crate mrkl;
+
+use mrkl::hash::{Hasher, NodeHasher, PairHasher};
+use mrkl::tree::{Builder, Children, IncrementalRoot, Node};
+
+const MODULUS: u64 = 0xffff_ffff_0000_0001;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+struct Fp(u64);
+
+impl Fp {
+    fn new(value: u64) -> Self {
+        Fp(value % MODULUS)
+    }
+
+    fn mix(self, other: Fp) -> Self {
+        let m = u128::from(MODULUS);
+        let x = (u128::from(self.0) * 7 + u128::from(other.0) + 3) % m;
+        Fp((x * x % m) as u64)
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct FieldHasher;
+
+impl Hasher<u64> for FieldHasher {
+    fn hash_input(&self, input: &u64) -> Fp {
+        Fp::new(0).mix(Fp::new(*input))
+    }
+}
+
+impl NodeHasher for FieldHasher {
+    type HashOutput = Fp;
+
+    fn hash_children<'a, L>(&'a self, iter: Children<'a, Fp, L>) -> Fp {
+        iter.fold(Fp::new(1), |acc, node| acc.mix(*node.hash()))
+    }
+}
+
+impl PairHasher for FieldHasher {
+    fn hash_pair(&self, left: &Fp, _: usize, right: &Fp, _: usize) -> Fp {
+        Fp::new(1).mix(*left).mix(*right)
+    }
+}
+
+#[test]
+fn build_and_traverse() {
+    let builder = Builder::<FieldHasher, _>::new();
+    let input: Vec<u64> = (0..10).collect();
+    let tree = builder.complete_tree_from(input.clone()).unwrap();
+    let other = builder.complete_tree_from(input.clone()).unwrap();
+    assert_eq!(tree, other);
+    let leaves: Vec<Fp> = tree.leaves().map(|ln| *ln.hash()).collect();
+    let expected: Vec<Fp> =
+        input.iter().map(|x| FieldHasher.hash_input(x)).collect();
+    assert_eq!(leaves, expected);
+    let count = tree.fold(|_| 1, |_, counts: Vec<usize>| counts.iter().sum());
+    assert_eq!(count, 10);
+
+    let full = builder.full_tree_from(input.clone()).unwrap();
+    let mut acc = IncrementalRoot::<FieldHasher>::default();
+    for x in &input {
+        acc.push(x);
+    }
+    assert_eq!(acc.current_root(), Some(*full.root().hash()));
+
+    let changed = builder
+        .complete_tree_from(input.iter().map(|x| x + 1))
+        .unwrap();
+    assert!(changed != tree);
+    if let Node::Hash(ref hn) = *tree.root() {
+        assert!(hn.verify_own_hash(&FieldHasher));
+    } else {
+        unreachable!()
+    }
+}
+
+#[cfg(feature = "parallel")]
+#[test]
+fn parallel_build() {
+    use mrkl::tree::parallel;
+
+    let builder = Builder::<FieldHasher, _>::new();
+    let par_builder = parallel::Builder::<FieldHasher, _>::new();
+    let input: Vec<u64> = (0..100).collect();
+    let tree = par_builder.complete_tree_from(input.clone()).unwrap();
+    assert_eq!(tree, builder.complete_tree_from(input).unwrap());
+}
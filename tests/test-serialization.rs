@@ -1,13 +1,20 @@
 #![cfg(test)]
 #![cfg(all(feature = "serialization", feature = "digest"))]
 
+extern crate generic_array;
 extern crate mrkl;
+extern crate serde;
+#[macro_use]
+extern crate serde_derive;
 extern crate serde_json;
 extern crate sha2;
 
+use generic_array::typenum::U2;
+use generic_array::GenericArray;
 use mrkl::digest::ByteDigestHasher;
 use mrkl::leaf;
 use mrkl::tree::Builder;
+use serde::de::DeserializeSeed;
 use sha2::Sha256;
 
 type Hasher = ByteDigestHasher<Sha256>;
@@ -25,3 +32,63 @@ fn serialize() {
     let json = serde_json::to_string_pretty(&tree).unwrap();
     println!("{}", json);
 }
+
+#[test]
+fn deserialize_verified() {
+    let hasher = Hasher::new();
+    let leaf_extractor = leaf::extract_with(|input: &[u8]| {
+        String::from_utf8(input.to_vec()).unwrap()
+    });
+    let builder = Builder::from_hasher_leaf_data(hasher, leaf_extractor);
+    let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+    let json = serde_json::to_string(&tree).unwrap();
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    let restored = (&builder).deserialize(&mut deserializer).unwrap();
+    assert_eq!(restored, tree);
+    let data: Vec<_> = restored.leaves().map(|ln| ln.data().clone()).collect();
+    let expected: Vec<_> = tree.leaves().map(|ln| ln.data().clone()).collect();
+    assert_eq!(data, expected);
+}
+
+#[test]
+fn deserialize_tampered() {
+    let hasher = Hasher::new();
+    let leaf_extractor = leaf::extract_with(|input: &[u8]| {
+        String::from_utf8(input.to_vec()).unwrap()
+    });
+    let builder = Builder::from_hasher_leaf_data(hasher, leaf_extractor);
+    let tree = builder.complete_tree_from(TEST_DATA.chunks(10)).unwrap();
+    let mut value = serde_json::to_value(&tree).unwrap();
+    value["root"]["Hash"]["hash"][0] = serde_json::Value::from(0);
+    value["root"]["Hash"]["hash"][1] = serde_json::Value::from(0);
+    let json = serde_json::to_string(&value).unwrap();
+    let mut deserializer = serde_json::Deserializer::from_str(&json);
+    (&builder).deserialize(&mut deserializer).unwrap_err();
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct HexHash {
+    #[serde(serialize_with = "mrkl::hex::to_hex")]
+    #[serde(deserialize_with = "mrkl::hex::from_hex")]
+    hash: GenericArray<u8, U2>,
+}
+
+#[test]
+fn hex_round_trip() {
+    let value: HexHash = serde_json::from_str(r#"{"hash":"0aF9"}"#).unwrap();
+    assert_eq!(value.hash.as_slice(), &[0x0a, 0xf9]);
+    let json = serde_json::to_string(&value).unwrap();
+    assert_eq!(json, r#"{"hash":"0af9"}"#);
+}
+
+#[test]
+fn hex_malformed() {
+    let err = serde_json::from_str::<HexHash>(r#"{"hash":"0g12"}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("hexadecimal digits"), "{}", err);
+    let err = serde_json::from_str::<HexHash>(r#"{"hash":"0a1"}"#)
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("invalid length 3"), "{}", err);
+}
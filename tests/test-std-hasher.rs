@@ -0,0 +1,37 @@
+#![cfg(test)]
+
+// Trees hashed with the standard library's hashing facilities.
+
+extern crate mrkl;
+
+use mrkl::hash::{Hasher, NodeHasher, StdHasherAdapter};
+use mrkl::tree::{Builder, Node};
+
+use std::collections::hash_map::DefaultHasher;
+use std::hash::BuildHasherDefault;
+
+type StdHasher = StdHasherAdapter<BuildHasherDefault<DefaultHasher>>;
+
+#[test]
+fn build_with_default_hasher() {
+    let builder = Builder::<StdHasher, _>::new();
+    let input: Vec<&str> = vec!["ant", "bee", "cat", "dog", "eel"];
+    let tree = builder.complete_tree_from(input.clone()).unwrap();
+    let other = builder.complete_tree_from(input.clone()).unwrap();
+    assert_eq!(tree.root().hash(), other.root().hash());
+
+    let hasher = StdHasher::default();
+    for (leaf, s) in tree.leaves().zip(&input) {
+        assert_eq!(*leaf.hash(), hasher.hash_input(s));
+    }
+    if let Node::Hash(ref hn) = *tree.root() {
+        assert_eq!(*hn.hash(), hasher.hash_children(hn.children()));
+    } else {
+        unreachable!()
+    }
+
+    let changed = builder
+        .complete_tree_from(vec!["ant", "bee", "cat", "dog", "elk"])
+        .unwrap();
+    assert!(changed.root().hash() != tree.root().hash());
+}